@@ -1,22 +1,21 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let descriptor_path =
         std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap()).join("descriptor.bin");
+    let protos = [
+        "proto/example.proto",
+        "proto/tgis.proto",
+        "proto/health.proto",
+        "proto/types.proto",
+    ];
+    let mut config = prost_build::Config::new();
+    prost_reflect_build::Builder::new()
+        .file_descriptor_set_path(descriptor_path)
+        .file_descriptor_set_bytes("crate::FILE_DESCRIPTOR_SET")
+        .configure(&mut config, &protos, &["proto"])?;
     tonic_build::configure()
         .build_client(true)
-        .build_server(false)
-        .file_descriptor_set_path(descriptor_path)
-        .type_attribute(
-            ".",
-            "#[derive(serde::Deserialize)] #[serde(rename_all = \"snake_case\")]",
-        )
-        .compile_protos(
-            &[
-                "proto/example.proto",
-                "proto/tgis.proto",
-                "proto/health.proto",
-            ],
-            &["proto"],
-        )
+        .build_server(true)
+        .compile_protos_with_config(config, &protos, &["proto"])
         .unwrap_or_else(|e| panic!("protobuf compilation failed: {}", e));
     Ok(())
 }
@@ -5,10 +5,22 @@ mod pb {
 #[cfg(test)]
 mod tests {
     use super::pb::{health_client::HealthClient, HealthCheckRequest, HealthCheckResponse};
-    use grpcmock::prelude::*;
-    use tonic::transport::Channel;
+    use crate::{
+        eventually,
+        hello::pb::{hello_client::HelloClient, HelloRequest, HelloResponse},
+    };
+    use futures::{StreamExt, TryStreamExt};
+    use grpcmock::{mock::MockFile, prelude::*};
+    use tonic::{transport::Channel, Code};
+    use tonic_reflection::pb::v1::{
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+        ServerReflectionRequest,
+    };
 
     grpcmock::generate_server!("grpc.health.v1.Health", MockHealthServer);
+    grpcmock::generate_server!("example.Hello", MockHelloServerOnly);
+    grpcmock::generate_server!(["grpc.health.v1.Health", "example.Hello"], MockMultiServer);
 
     #[tokio::test]
     async fn test_health() -> Result<(), anyhow::Error> {
@@ -36,4 +48,458 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_multiple_services() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert(
+            GrpcMethod::new("grpc.health.v1.Health", "Check")?,
+            Mock::unary(
+                HealthCheckRequest { service: "".into() },
+                HealthCheckResponse { status: 1 },
+            ),
+        );
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server = MockMultiServer::start(mocks).await?;
+        assert_eq!(
+            server.services(),
+            ["grpc.health.v1.Health", "example.Hello"]
+        );
+        let channel = server.channel().await?;
+
+        let response = HealthClient::new(channel.clone())
+            .check(HealthCheckRequest { service: "".into() })
+            .await?;
+        assert_eq!(response.into_inner().status, 1);
+
+        let response = HelloClient::new(channel)
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multiple_services_from_file() -> Result<(), anyhow::Error> {
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file_with_descriptors(
+            "stubs/scenarios/hello_and_health.yaml",
+            &descriptors,
+        )?;
+        assert_eq!(mocks.len(), 3);
+        let server = MockMultiServer::start(mocks).await?;
+        let channel = server.channel().await?;
+
+        let response = HealthClient::new(channel.clone())
+            .check(HealthCheckRequest { service: "".into() })
+            .await?;
+        assert_eq!(response.into_inner().status, 1);
+
+        let mut client = HelloClient::new(channel);
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+        let response = client
+            .hello_server_streaming(HelloRequest {
+                name: "Dan, Paul".into(),
+            })
+            .await?;
+        let messages = response
+            .into_inner()
+            .map_ok(|response| response.message)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(messages, ["Hello Dan!", "Hello Paul!"]);
+
+        // Reading a single method fails for files with multiple methods
+        let result =
+            MockFile::read_with_descriptors("stubs/scenarios/hello_and_health.yaml", &descriptors);
+        assert!(result.is_err_and(|error| {
+            error.to_string() == "invalid: mock file should contain a single method, found 3"
+        }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_runtime_services() -> Result<(), anyhow::Error> {
+        // Service names known only at runtime, e.g. from configuration
+        let services = vec![String::from("example.Hello")];
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server =
+            MockHealthServer::start_with(MockServer::builder().services(services), mocks).await?;
+        let channel = server.channel().await?;
+
+        let response = HelloClient::new(channel.clone())
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        // Services not hosted by the server are unimplemented
+        let server = MockHelloServerOnly::start(MockSet::new()).await?;
+        let response = HealthClient::new(server.channel().await?)
+            .check(HealthCheckRequest { service: "".into() })
+            .await;
+        assert!(response.is_err_and(|e| e.code() == Code::Unimplemented));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multiple_services_with_invalid_mocks() {
+        let mut mocks = MockSet::new();
+        mocks.insert(
+            GrpcMethod::new("WrongService", "Hello").unwrap(),
+            Mock::unary(
+                HelloRequest { name: "you".into() },
+                HelloResponse {
+                    message: "Hello you!".into(),
+                },
+            ),
+        );
+        assert!(MockMultiServer::start(mocks).await.is_err_and(|error| {
+            error.to_string()
+                == "invalid: all mocks must be for `grpc.health.v1.Health`, `example.Hello` services"
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_server() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server = MockServer::builder().in_memory().start(mocks).await?;
+        assert!(server.services().is_empty());
+        let channel = server.channel().await?;
+
+        let response = HelloClient::new(channel.clone())
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        // Unknown method
+        let response = HelloClient::new(channel.clone())
+            .hello_server_streaming(HelloRequest { name: "Dan".into() })
+            .await;
+        assert!(response.is_err_and(|e| e.code() == Code::Unimplemented));
+
+        // Unknown service
+        let response = HealthClient::new(channel)
+            .check(HealthCheckRequest { service: "".into() })
+            .await;
+        assert!(response.is_err_and(|e| e.code() == Code::Unimplemented));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_builtin_health() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server =
+            MockHelloServerOnly::start_with(MockServer::builder().health().in_memory(), mocks)
+                .await?;
+        let mut client = HealthClient::new(server.channel().await?);
+
+        // Overall server health
+        let response = client
+            .check(HealthCheckRequest { service: "".into() })
+            .await?;
+        assert_eq!(response.into_inner().status, ServingStatus::Serving as i32);
+
+        let response = client
+            .check(HealthCheckRequest {
+                service: "example.Hello".into(),
+            })
+            .await?;
+        assert_eq!(response.into_inner().status, ServingStatus::Serving as i32);
+
+        let mut stream = client
+            .watch(HealthCheckRequest {
+                service: "example.Hello".into(),
+            })
+            .await?
+            .into_inner();
+        let response = stream.next().await.unwrap()?;
+        assert_eq!(response.status, ServingStatus::Serving as i32);
+
+        server
+            .health()
+            .unwrap()
+            .set_service_status("example.Hello", ServingStatus::NotServing)
+            .await;
+        let response = stream.next().await.unwrap()?;
+        assert_eq!(response.status, ServingStatus::NotServing as i32);
+
+        let response = client
+            .check(HealthCheckRequest {
+                service: "example.Hello".into(),
+            })
+            .await?;
+        assert_eq!(
+            response.into_inner().status,
+            ServingStatus::NotServing as i32
+        );
+
+        // Mocked services are unaffected
+        let response = HelloClient::new(server.channel().await?)
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_builtin_health_with_runtime_services() -> Result<(), anyhow::Error> {
+        let server = MockServer::builder()
+            .health()
+            .reflection(crate::FILE_DESCRIPTOR_SET)
+            .in_memory()
+            .start(MockSet::new())
+            .await?;
+        let channel = server.channel().await?;
+
+        async fn status(channel: &Channel) -> Result<i32, tonic::Status> {
+            let response = HealthClient::new(channel.clone())
+                .check(HealthCheckRequest {
+                    service: "example.Hello".into(),
+                })
+                .await?;
+            Ok(response.into_inner().status)
+        }
+
+        async fn services(channel: &Channel) -> Result<Vec<String>, anyhow::Error> {
+            let request = ServerReflectionRequest {
+                host: String::new(),
+                message_request: Some(MessageRequest::ListServices(String::new())),
+            };
+            let mut responses = ServerReflectionClient::new(channel.clone())
+                .server_reflection_info(futures::stream::iter([request]))
+                .await?
+                .into_inner();
+            let Some(MessageResponse::ListServicesResponse(response)) =
+                responses.message().await?.unwrap().message_response
+            else {
+                panic!("expected list services response");
+            };
+            let mut services = response
+                .service
+                .into_iter()
+                .map(|service| service.name)
+                .collect::<Vec<_>>();
+            services.sort();
+            Ok(services)
+        }
+
+        assert!(status(&channel)
+            .await
+            .is_err_and(|e| e.code() == Code::NotFound));
+        assert_eq!(
+            services(&channel).await?,
+            [
+                "grpc.health.v1.Health",
+                "grpc.reflection.v1.ServerReflection"
+            ]
+        );
+
+        // Services added with mocks at runtime are advertised
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        server.set_mocks(mocks)?;
+        eventually(|| async { Ok(status(&channel).await.is_ok()) }).await?;
+        assert_eq!(status(&channel).await?, ServingStatus::Serving as i32);
+        assert_eq!(
+            services(&channel).await?,
+            [
+                "example.Hello",
+                "grpc.health.v1.Health",
+                "grpc.reflection.v1.ServerReflection"
+            ]
+        );
+
+        // And no longer advertised when their mocks are removed
+        server.update_mocks(|mocks| mocks.clear())?;
+        eventually(|| async { Ok(status(&channel).await.is_err()) }).await?;
+        assert!(status(&channel)
+            .await
+            .is_err_and(|e| e.code() == Code::NotFound));
+        assert_eq!(
+            services(&channel).await?,
+            [
+                "grpc.health.v1.Health",
+                "grpc.reflection.v1.ServerReflection"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_builtin_health_with_reloads() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server = MockServer::builder()
+            .health()
+            .in_memory()
+            .start(mocks.clone())
+            .await?;
+        let client = HealthClient::new(server.channel().await?);
+        let status = move || {
+            let mut client = client.clone();
+            async move {
+                client
+                    .check(HealthCheckRequest {
+                        service: "example.Hello".into(),
+                    })
+                    .await
+                    .map(|response| response.into_inner().status)
+            }
+        };
+
+        // Reloading mocks on the runtime's only thread, while clients check health, doesn't block it
+        let checks = tokio::spawn({
+            let status = status.clone();
+            async move {
+                loop {
+                    let _ = status().await;
+                }
+            }
+        });
+        for _ in 0..10 {
+            server.set_mocks(MockSet::new())?;
+            server.set_mocks(mocks.clone())?;
+            tokio::task::yield_now().await;
+        }
+        eventually(|| async { Ok(status().await? == ServingStatus::Serving as i32) }).await?;
+        server.set_mocks(MockSet::new())?;
+        eventually(|| async { Ok(status().await.is_err_and(|e| e.code() == Code::NotFound)) })
+            .await?;
+        checks.abort();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_passthrough() -> Result<(), anyhow::Error> {
+        // A real upstream, serving the built-in health service and mocks
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let upstream =
+            MockHelloServerOnly::start_with(MockServer::builder().health().in_memory(), mocks)
+                .await?;
+
+        // Mocks some requests, forwarding others to the upstream
+        let mut mocks = MockSet::new();
+        mocks.insert(
+            GrpcMethod::new("example.Hello", "HelloUnary")?,
+            Mock::unary(
+                HelloRequest { name: "Dan".into() },
+                HelloResponse {
+                    message: "Hi Dan!".into(),
+                },
+            ),
+        );
+        let server = MockMultiServer::start_with(
+            MockServer::builder().passthrough(upstream.channel().await?),
+            mocks.clone(),
+        )
+        .await?;
+        let channel = server.channel().await?;
+
+        let mut client = HelloClient::new(channel.clone());
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hi Dan!");
+
+        // Unmatched requests are forwarded, with errors relayed from the upstream
+        let response = client
+            .hello_unary(HelloRequest {
+                name: "Paul".into(),
+            })
+            .await;
+        assert!(response.is_err_and(|e| e.code() == Code::NotFound));
+
+        // Methods without mocks are forwarded, with statuses relayed from trailers
+        let response = HealthClient::new(channel.clone())
+            .check(HealthCheckRequest {
+                service: "example.Hello".into(),
+            })
+            .await?;
+        assert_eq!(response.into_inner().status, ServingStatus::Serving as i32);
+
+        let proxied = server
+            .journal()
+            .iter()
+            .map(|entry| (entry.method.name().to_string(), entry.proxied))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            proxied,
+            [
+                ("HelloUnary".to_string(), false),
+                ("HelloUnary".to_string(), true),
+                ("Check".to_string(), true)
+            ]
+        );
+        assert_eq!(upstream.journal().len(), 1);
+
+        // Streams cancelled by the client are added to the journal
+        let mut statuses = HealthClient::new(channel)
+            .watch(HealthCheckRequest {
+                service: "example.Hello".into(),
+            })
+            .await?
+            .into_inner();
+        assert_eq!(
+            statuses.message().await?.unwrap().status,
+            ServingStatus::Serving as i32
+        );
+        drop(statuses);
+        eventually(|| async {
+            let journal = server.journal();
+            Ok(journal.len() == 4 && journal[3].method.name() == "Watch" && journal[3].proxied)
+        })
+        .await?;
+
+        // Requests for services the server doesn't host are not forwarded
+        let server = MockHelloServerOnly::start_with(
+            MockServer::builder().passthrough(upstream.channel().await?),
+            mocks.clone(),
+        )
+        .await?;
+        let response = HealthClient::new(server.channel().await?)
+            .check(HealthCheckRequest {
+                service: "example.Hello".into(),
+            })
+            .await;
+        assert!(response.is_err_and(|e| e.code() == Code::Unimplemented));
+        assert_eq!(upstream.journal().len(), 1);
+
+        // Nor, for servers hosting all services in their mocks, for services without mocks
+        let server = MockServer::builder()
+            .passthrough(upstream.channel().await?)
+            .start(mocks)
+            .await?;
+        let channel = server.channel().await?;
+        let response = HealthClient::new(channel.clone())
+            .check(HealthCheckRequest {
+                service: "example.Hello".into(),
+            })
+            .await;
+        assert!(response.is_err_and(|e| e.code() == Code::Unimplemented));
+        assert_eq!(upstream.journal().len(), 1);
+        let response = HelloClient::new(channel)
+            .hello_unary(HelloRequest {
+                name: "Paul".into(),
+            })
+            .await;
+        assert!(response.is_err_and(|e| e.code() == Code::NotFound));
+        assert_eq!(upstream.journal().len(), 2);
+
+        Ok(())
+    }
 }
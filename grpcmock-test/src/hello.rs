@@ -1,13 +1,52 @@
-mod pb {
+pub(crate) mod pb {
     tonic::include_proto!("example");
 }
 
 #[cfg(test)]
 mod tests {
-    use super::pb::{hello_client::HelloClient, HelloRequest, HelloResponse};
-    use futures::StreamExt;
-    use grpcmock::prelude::*;
-    use tonic::transport::Channel;
+    use std::{
+        net::{Ipv4Addr, Ipv6Addr},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::pb::{
+        hello_client::HelloClient,
+        hello_server::{Hello, HelloServer},
+        HelloRequest, HelloResponse,
+    };
+    use bytes::Bytes;
+    use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+    use grpcmock::{
+        mock::{MockFile, MockFormat},
+        prelude::*,
+        tls::TestCa,
+    };
+    use http_body::Frame;
+    use http_body_util::{BodyExt, StreamBody};
+    use hyper_util::rt::TokioIo;
+    use prost::Message;
+    use tokio::net::UnixStream;
+    use tonic::{
+        body::BoxBody,
+        metadata::MetadataMap,
+        transport::{
+            server::TcpIncoming, Certificate, Channel, ClientTlsConfig, Endpoint, Identity, Server,
+            Uri,
+        },
+        Code,
+    };
+    use tonic_reflection::pb::v1::{
+        server_reflection_client::ServerReflectionClient,
+        server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+        ServerReflectionRequest,
+    };
+    use tower::{service_fn, ServiceExt};
+
+    use crate::eventually;
 
     grpcmock::generate_server!("example.Hello", MockHelloServer);
 
@@ -64,6 +103,1048 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_hello_with_descriptors() -> Result<(), anyhow::Error> {
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file_with_descriptors("stubs/hello/unary.yaml", &descriptors)?;
+        mocks
+            .insert_from_file_with_descriptors("stubs/hello/server_streaming.yaml", &descriptors)?;
+
+        let server = MockHelloServer::start(mocks).await?;
+        let mut client = HelloClient::new(server.channel().await?);
+
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        let response = client
+            .hello_server_streaming(HelloRequest {
+                name: "Dan, Paul, Gaurav".into(),
+            })
+            .await?;
+        let messages = response
+            .into_inner()
+            .map_ok(|response| response.message)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(messages, ["Hello Dan!", "Hello Paul!", "Hello Gaurav!"]);
+
+        // Mock files for services missing from the descriptors are rejected
+        let result = MockSet::new()
+            .insert_from_file_with_descriptors("stubs/hello/unary.yaml", &DescriptorPool::new());
+        assert!(result.is_err_and(|error| {
+            error.to_string() == "invalid: service `example.Hello` not found in descriptors"
+        }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_load_dir() -> Result<(), anyhow::Error> {
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let mut mocks = MockSet::new();
+        mocks.load_dir("stubs/hello", "**/*.yaml", &descriptors)?;
+        let mut methods = mocks.keys().map(GrpcMethod::name).collect::<Vec<_>>();
+        methods.sort();
+        assert_eq!(
+            methods,
+            [
+                "HelloBidiStreaming",
+                "HelloClientStreaming",
+                "HelloServerStreaming",
+                "HelloUnary"
+            ]
+        );
+
+        // Types can be resolved from a registry of prost-generated types instead of descriptors
+        let mut registry = TypeRegistry::new();
+        registry.register::<HelloRequest, HelloResponse>(GrpcMethod::new(
+            "example.Hello",
+            "HelloUnary",
+        )?);
+        let mut mocks = MockSet::new();
+        mocks.load_dir("stubs/hello", "unary.yaml", &registry)?;
+        let server = MockHelloServer::start(mocks).await?;
+        let mut client = HelloClient::new(server.channel().await?);
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        // Files for unregistered methods are rejected
+        let result = MockSet::new().load_dir("stubs/hello", "*streaming.yaml", &registry);
+        assert!(result.is_err_and(|error| {
+            error.to_string()
+                == "invalid: mock file `stubs/hello/bidi_streaming.yaml`: invalid: method `/example.Hello/HelloBidiStreaming` not registered"
+        }));
+
+        // Symlink cycles are followed once
+        #[cfg(unix)]
+        {
+            let dir = tempfile::tempdir()?;
+            std::fs::copy("stubs/hello/unary.yaml", dir.path().join("unary.yaml"))?;
+            std::os::unix::fs::symlink(dir.path(), dir.path().join("loop"))?;
+            let mut mocks = MockSet::new();
+            mocks.load_dir(dir.path(), "**/*.yaml", &descriptors)?;
+            assert_eq!(mocks.values().map(Vec::len).sum::<usize>(), 1);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_mock_file_formats() -> Result<(), anyhow::Error> {
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let mut mocks = MockSet::new();
+        // Formats are selected by extension
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/formats/unary.json")?;
+        mocks.load_dir("stubs/formats", "*.toml", &descriptors)?;
+
+        let server = MockHelloServer::start(mocks).await?;
+        let mut client = HelloClient::new(server.channel().await?);
+
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        let response = client
+            .hello_server_streaming(HelloRequest {
+                name: "Dan, Paul, Gaurav".into(),
+            })
+            .await?;
+        let messages = response
+            .into_inner()
+            .map_ok(|response| response.message)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(messages, ["Hello Dan!", "Hello Paul!", "Hello Gaurav!"]);
+
+        let request_stream = futures::stream::iter(
+            ["Dan", "Gaurav", "Paul"].map(|name| HelloRequest { name: name.into() }),
+        );
+        let response = client.hello_client_streaming(request_stream).await?;
+        assert_eq!(
+            response.into_inner().message,
+            "Hello Dan, Gaurav, and Paul!"
+        );
+
+        // Bodies can be given as text format or wire bytes
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file_with_descriptors("stubs/formats/encoded.yaml", &descriptors)?;
+        let server = MockHelloServer::start(mocks).await?;
+        let mut client = HelloClient::new(server.channel().await?);
+        for (name, message) in [("Dan", "Hello Dan!"), ("Gaurav", "Hello Gaurav!")] {
+            let response = client
+                .hello_unary(HelloRequest { name: name.into() })
+                .await?;
+            assert_eq!(response.into_inner().message, message);
+        }
+
+        // File bodies may not escape the mock file's directory
+        let dir = tempfile::tempdir()?;
+        for body in ["file: ../hello_dan.binpb", "file: /etc/hosts"] {
+            let path = dir.path().join("escape.yaml");
+            let yaml = std::fs::read_to_string("stubs/formats/encoded.yaml")?
+                .replace("file: hello_dan.binpb", body);
+            std::fs::write(&path, yaml)?;
+            let result = MockSet::new().insert_from_file_with_descriptors(&path, &descriptors);
+            assert!(result.is_err_and(|error| error.to_string().contains("without `..`")));
+        }
+
+        // Bodies can be given as native tables
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file_with_descriptors(
+            "stubs/native/client_streaming.toml",
+            &descriptors,
+        )?;
+        let server = MockHelloServer::start(mocks).await?;
+        let mut client = HelloClient::new(server.channel().await?);
+        let request_stream = futures::stream::iter(
+            ["Dan", "Gaurav", "Paul"].map(|name| HelloRequest { name: name.into() }),
+        );
+        let response = client.hello_client_streaming(request_stream).await?;
+        assert_eq!(
+            response.into_inner().message,
+            "Hello Dan, Gaurav, and Paul!"
+        );
+
+        // Files of every format are loaded, skipping other files such as `hello_dan.binpb`
+        let mut mocks = MockSet::new();
+        mocks.load_dir("stubs/formats", "**/*", &descriptors)?;
+        let mut methods = mocks.keys().map(GrpcMethod::name).collect::<Vec<_>>();
+        methods.sort();
+        assert_eq!(
+            methods,
+            ["HelloClientStreaming", "HelloServerStreaming", "HelloUnary"]
+        );
+        assert_eq!(
+            mocks[&GrpcMethod::new("example.Hello", "HelloUnary")?].len(),
+            3
+        );
+
+        // Formats can be given explicitly
+        let yaml = std::fs::read_to_string("stubs/hello/unary.yaml")?;
+        let json = std::fs::read_to_string("stubs/formats/unary.json")?;
+        assert_eq!(
+            serde_json::to_value(MockFile::parse_str(&yaml, MockFormat::Yaml)?)?,
+            serde_json::to_value(MockFile::parse_str(&json, MockFormat::Json)?)?
+        );
+
+        // Unknown extensions are rejected
+        let result = MockFile::parse("stubs/formats/unary.txt");
+        assert!(result.is_err_and(|error| {
+            error.to_string() == "invalid: unknown mock file format for `stubs/formats/unary.txt`"
+        }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_export() -> Result<(), anyhow::Error> {
+        let hello = |name: &str| HelloRequest { name: name.into() };
+        let reply = |message: &str| HelloResponse {
+            message: message.into(),
+        };
+        let mut mocks = MockSet::new();
+        mocks.insert(
+            GrpcMethod::new("example.Hello", "HelloUnary")?,
+            Mock::unary(hello("Dan"), reply("Hello Dan!")),
+        );
+        mocks.insert(
+            GrpcMethod::new("example.Hello", "HelloClientStreaming")?,
+            Mock::client_streaming([hello("Dan"), hello("Paul")], reply("Hello Dan and Paul!")),
+        );
+
+        // Bodies are converted to JSON using a registry of prost-generated types
+        let mut registry = TypeRegistry::new();
+        for method in ["HelloUnary", "HelloClientStreaming"] {
+            registry
+                .register::<HelloRequest, HelloResponse>(GrpcMethod::new("example.Hello", method)?);
+        }
+        let dir = tempfile::tempdir()?;
+        mocks.write_dir(dir.path(), &registry)?;
+        let yaml = std::fs::read_to_string(dir.path().join("example.Hello/HelloUnary.yaml"))?;
+        assert_eq!(
+            yaml,
+            "service: example.Hello\n\
+             method: HelloUnary\n\
+             mocks:\n\
+             - request:\n    body: '{\"name\":\"Dan\"}'\n  \
+             response:\n    code: 200\n    body: '{\"message\":\"Hello Dan!\"}'\n"
+        );
+
+        // Exported mocks can be read back, using descriptors
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let mut loaded = MockSet::new();
+        loaded.load_dir(dir.path(), "**/*.yaml", &descriptors)?;
+        let path = dir.path().join("mocks.yaml");
+        mocks.write_file(&path, &descriptors)?;
+        loaded.insert_from_file_with_descriptors(&path, &descriptors)?;
+
+        let server = MockHelloServer::start(loaded).await?;
+        let mut client = HelloClient::new(server.channel().await?);
+        let response = client.hello_unary(hello("Dan")).await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+        let request_stream = futures::stream::iter([hello("Dan"), hello("Paul")]);
+        let response = client.hello_client_streaming(request_stream).await?;
+        assert_eq!(response.into_inner().message, "Hello Dan and Paul!");
+        assert_eq!(server.mocks().values().map(Vec::len).sum::<usize>(), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_record() -> Result<(), anyhow::Error> {
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let mut mocks = MockSet::new();
+        mocks.load_dir("stubs/hello", "**/*.yaml", &descriptors)?;
+        let upstream = MockHelloServer::start(mocks).await?;
+
+        // Calls are forwarded to the upstream and recorded
+        let dir = tempfile::tempdir()?;
+        let server = MockServer::builder()
+            .record(upstream.channel().await?, dir.path(), descriptors.clone())
+            .start(MockSet::new())
+            .await?;
+
+        let call_all = |channel| async move {
+            let mut client = HelloClient::new(channel);
+            let hello = |name: &str| HelloRequest { name: name.into() };
+            let names = || ["Dan", "Gaurav", "Paul"].map(hello);
+            let unary = client.hello_unary(hello("Dan")).await?.into_inner();
+            let error = client.hello_unary(hello("Unknown")).await.unwrap_err();
+            let client_streaming = client
+                .hello_client_streaming(futures::stream::iter(names()))
+                .await?
+                .into_inner();
+            let server_streaming = client
+                .hello_server_streaming(hello("Dan, Paul, Gaurav"))
+                .await?
+                .into_inner()
+                .try_collect::<Vec<_>>()
+                .await?;
+            let bidi_streaming = client
+                .hello_bidi_streaming(futures::stream::iter(names()))
+                .await?
+                .into_inner()
+                .try_collect::<Vec<_>>()
+                .await?;
+            Ok::<_, anyhow::Error>((
+                unary,
+                error.code(),
+                client_streaming,
+                server_streaming,
+                bidi_streaming,
+            ))
+        };
+        let responses = call_all(server.channel().await?).await?;
+        assert_eq!(responses.0.message, "Hello Dan!");
+        assert_eq!(responses.1, Code::NotFound);
+        assert_eq!(responses.4.len(), 3);
+        assert_eq!(upstream.journal().len(), 5);
+        // Recorded requests are not marked as proxied, which is reserved for passthrough
+        assert!(server.journal().iter().all(|entry| !entry.proxied));
+        assert_eq!(server.recorded_mocks().map(|mocks| mocks.len()), Some(4));
+
+        // Recorded mock files replay the same responses
+        let mut mocks = MockSet::new();
+        mocks.load_dir(dir.path(), "example.Hello/*.yaml", &descriptors)?;
+        let replay = MockHelloServer::start(mocks).await?;
+        assert_eq!(call_all(replay.channel().await?).await?, responses);
+
+        Ok(())
+    }
+
+    /// A real Hello server, failing with metadata and replying to bidi-streaming requests as they arrive.
+    struct Upstream;
+
+    #[tonic::async_trait]
+    impl Hello for Upstream {
+        async fn hello_unary(
+            &self,
+            _request: tonic::Request<HelloRequest>,
+        ) -> Result<tonic::Response<HelloResponse>, tonic::Status> {
+            Err(upstream_error(Code::DataLoss, "lost"))
+        }
+
+        async fn hello_client_streaming(
+            &self,
+            _request: tonic::Request<tonic::Streaming<HelloRequest>>,
+        ) -> Result<tonic::Response<HelloResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("not implemented"))
+        }
+
+        type HelloServerStreamingStream = BoxStream<'static, Result<HelloResponse, tonic::Status>>;
+
+        async fn hello_server_streaming(
+            &self,
+            request: tonic::Request<HelloRequest>,
+        ) -> Result<tonic::Response<Self::HelloServerStreamingStream>, tonic::Status> {
+            let response = HelloResponse {
+                message: format!("Hello {}!", request.into_inner().name),
+            };
+            let error = upstream_error(Code::Aborted, "aborted");
+            Ok(tonic::Response::new(
+                futures::stream::iter([Ok(response), Err(error)]).boxed(),
+            ))
+        }
+
+        type HelloBidiStreamingStream = BoxStream<'static, Result<HelloResponse, tonic::Status>>;
+
+        async fn hello_bidi_streaming(
+            &self,
+            request: tonic::Request<tonic::Streaming<HelloRequest>>,
+        ) -> Result<tonic::Response<Self::HelloBidiStreamingStream>, tonic::Status> {
+            let responses = request.into_inner().map_ok(|request| HelloResponse {
+                message: format!("Hello {}!", request.name),
+            });
+            Ok(tonic::Response::new(responses.boxed()))
+        }
+    }
+
+    fn upstream_error(code: Code, message: &str) -> tonic::Status {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-reason", "upstream".parse().unwrap());
+        tonic::Status::with_metadata(code, message, metadata)
+    }
+
+    async fn start_upstream() -> Result<Channel, anyhow::Error> {
+        let listener = tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+        let incoming = TcpIncoming::from_listener(listener, true, None)
+            .map_err(|error| anyhow::anyhow!(error))?;
+        tokio::spawn(
+            Server::builder()
+                .add_service(HelloServer::new(Upstream))
+                .serve_with_incoming(incoming),
+        );
+        Ok(Endpoint::from_shared(format!("http://{addr}"))?
+            .connect()
+            .await?)
+    }
+
+    #[tokio::test]
+    async fn test_hello_record_interactive_bidi() -> Result<(), anyhow::Error> {
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let dir = tempfile::tempdir()?;
+        let server = MockServer::builder()
+            .record(start_upstream().await?, dir.path(), descriptors.clone())
+            .start(MockSet::new())
+            .await?;
+        let mut client = HelloClient::new(server.channel().await?);
+        let hello = |name: &str| HelloRequest { name: name.into() };
+
+        // Each request is sent only after the response to the previous one is received
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let mut responses = client.hello_bidi_streaming(rx).await?.into_inner();
+        for name in ["Dan", "Gaurav", "Paul"] {
+            tx.unbounded_send(hello(name))?;
+            let response = tokio::time::timeout(Duration::from_secs(5), responses.message())
+                .await??
+                .unwrap();
+            assert_eq!(response.message, format!("Hello {name}!"));
+        }
+        drop(tx);
+        assert!(responses.message().await?.is_none());
+
+        // The whole call is recorded once it ends
+        let mut mocks = MockSet::new();
+        mocks.load_dir(dir.path(), "example.Hello/*.yaml", &descriptors)?;
+        let replay = MockHelloServer::start(mocks).await?;
+        let mut client = HelloClient::new(replay.channel().await?);
+        let names = ["Dan", "Gaurav", "Paul"].map(hello);
+        let responses = client
+            .hello_bidi_streaming(futures::stream::iter(names))
+            .await?
+            .into_inner()
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[2].message, "Hello Paul!");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_record_status_and_metadata() -> Result<(), anyhow::Error> {
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let dir = tempfile::tempdir()?;
+        let server = MockServer::builder()
+            .record(start_upstream().await?, dir.path(), descriptors.clone())
+            .start(MockSet::new())
+            .await?;
+
+        let call_all = |channel| async move {
+            let mut client = HelloClient::new(channel);
+            let hello = || HelloRequest { name: "Dan".into() };
+            let status = |status: tonic::Status| {
+                let reason = status.metadata().get("x-reason").cloned();
+                (status.code(), status.message().to_string(), reason)
+            };
+            // Trailers-only response
+            let unary = status(client.hello_unary(hello()).await.unwrap_err());
+            // Status and metadata in trailers, after a response
+            let mut stream = client.hello_server_streaming(hello()).await?.into_inner();
+            let message = stream.message().await?.unwrap().message;
+            let streaming = status(stream.message().await.unwrap_err());
+            Ok::<_, anyhow::Error>((unary, message, streaming))
+        };
+        let responses = call_all(server.channel().await?).await?;
+        assert_eq!(
+            responses.0,
+            (
+                Code::DataLoss,
+                "lost".to_string(),
+                Some("upstream".parse()?)
+            )
+        );
+        assert_eq!(
+            responses.2,
+            (
+                Code::Aborted,
+                "aborted".to_string(),
+                Some("upstream".parse()?)
+            )
+        );
+
+        // Codes without an equivalent HTTP status code are recorded by name
+        let file = std::fs::read_to_string(dir.path().join("example.Hello/HelloUnary.yaml"))?;
+        assert!(file.contains("DATA_LOSS"));
+
+        // Recorded mock files replay the same status, message and metadata
+        let mut mocks = MockSet::new();
+        mocks.load_dir(dir.path(), "example.Hello/*.yaml", &descriptors)?;
+        let replay = MockHelloServer::start(mocks).await?;
+        assert_eq!(call_all(replay.channel().await?).await?, responses);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_admin() -> Result<(), anyhow::Error> {
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file_with_descriptors("stubs/hello/unary.yaml", &descriptors)?;
+        let server = MockHelloServer::start(mocks).await?;
+        let admin = grpcmock::admin::router(MockServer::clone(&server), descriptors);
+        let mut client = HelloClient::new(server.channel().await?);
+
+        async fn call(
+            admin: &axum::Router,
+            method: &str,
+            uri: &str,
+            body: &str,
+        ) -> Result<(http::StatusCode, String), anyhow::Error> {
+            let request = http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(body.to_string())?;
+            let response = admin.clone().oneshot(request).await?;
+            let status = response.status();
+            let body = response.into_body().collect().await?.to_bytes();
+            Ok((status, String::from_utf8(body.to_vec())?))
+        }
+
+        // List mocks as mock file sections
+        let (status, body) = call(&admin, "GET", "/mocks", "").await?;
+        assert_eq!(status, http::StatusCode::OK);
+        let files: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(files[0]["method"], "HelloUnary");
+        assert_eq!(
+            files[0]["mocks"][0]["response"]["body"],
+            r#"{"message":"Hello Dan!"}"#
+        );
+
+        // Add a mock
+        let mock = serde_json::json!({
+            "service": "example.Hello",
+            "method": "HelloUnary",
+            "mocks": [{
+                "request": { "body": r#"{"name": "Paul"}"# },
+                "response": { "body": r#"{"message": "Hello Paul!"}"# },
+            }],
+        });
+        let (status, _) = call(&admin, "POST", "/mocks", &mock.to_string()).await?;
+        assert_eq!(status, http::StatusCode::CREATED);
+        let response = client
+            .hello_unary(HelloRequest {
+                name: "Paul".into(),
+            })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Paul!");
+
+        // Invalid mocks are rejected
+        let mock = serde_json::json!({
+            "service": "example.Hello",
+            "method": "HelloUnary",
+            "mocks": [{
+                "request": { "body": r#"{"name": "Paul"}"# },
+                "response": { "body": [r#"{"message": "Hello Paul!"}"#] },
+            }],
+        });
+        let (status, body) = call(&admin, "POST", "/mocks", &mock.to_string()).await?;
+        assert_eq!(status, http::StatusCode::BAD_REQUEST);
+        assert!(body.contains("has a stream response body"));
+
+        // File bodies are only read from mock files, not from admin API requests
+        let mock = serde_json::json!({
+            "service": "example.Hello",
+            "method": "HelloUnary",
+            "mocks": [{
+                "request": { "body": r#"{"name": "Paul"}"# },
+                "response": { "body": "file: /etc/hosts" },
+            }],
+        });
+        let (status, body) = call(&admin, "POST", "/mocks", &mock.to_string()).await?;
+        assert_eq!(status, http::StatusCode::BAD_REQUEST);
+        assert!(body.contains("`file:` bodies are only supported in mock files"));
+
+        // Journal
+        let (status, body) = call(&admin, "GET", "/journal", "").await?;
+        assert_eq!(status, http::StatusCode::OK);
+        let entries: serde_json::Value = serde_json::from_str(&body)?;
+        assert_eq!(entries[0]["method"], "HelloUnary");
+        assert_eq!(entries[0]["body"], r#"{"name":"Paul"}"#);
+        assert_eq!(entries[0]["matched"], true);
+
+        // Remove mocks for a method
+        let (status, _) = call(&admin, "DELETE", "/mocks/example.Hello/HelloUnary", "").await?;
+        assert_eq!(status, http::StatusCode::NO_CONTENT);
+        let (status, _) = call(&admin, "DELETE", "/mocks/example.Hello/HelloUnary", "").await?;
+        assert_eq!(status, http::StatusCode::NOT_FOUND);
+        let result = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await;
+        assert!(result.is_err_and(|status| status.code() == Code::Unimplemented));
+
+        // Reset mocks and journal
+        let (status, _) = call(&admin, "POST", "/reset", "").await?;
+        assert_eq!(status, http::StatusCode::NO_CONTENT);
+        assert!(server.journal().is_empty());
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+        let result = client
+            .hello_unary(HelloRequest {
+                name: "Paul".into(),
+            })
+            .await;
+        assert!(result.is_err_and(|status| status.code() == Code::NotFound));
+
+        // Mocks replaced with set_mocks, e.g. when mock files are reloaded, are restored on reset
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/formats/unary.json")?;
+        server.set_mocks(mocks)?;
+        let (status, _) = call(&admin, "DELETE", "/mocks", "").await?;
+        assert_eq!(status, http::StatusCode::NO_CONTENT);
+        let (status, _) = call(&admin, "POST", "/mocks/reset", "").await?;
+        assert_eq!(status, http::StatusCode::NO_CONTENT);
+        assert_eq!(server.mocks().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_watch() -> Result<(), anyhow::Error> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("unary.yaml");
+        std::fs::copy("stubs/hello/unary.yaml", &path)?;
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let load = {
+            let (dir, descriptors) = (dir.path().to_path_buf(), descriptors.clone());
+            move || {
+                let mut mocks = MockSet::new();
+                mocks.load_dir(&dir, "*.yaml", &descriptors)?;
+                Ok(mocks)
+            }
+        };
+        let server = MockHelloServer::start(load()?).await?;
+        let failures = Arc::new(AtomicUsize::new(0));
+        let _watcher = server.watch([dir.path()], {
+            let failures = failures.clone();
+            move || {
+                let mocks = load();
+                if mocks.is_err() {
+                    failures.fetch_add(1, Ordering::SeqCst);
+                }
+                mocks
+            }
+        })?;
+        let mut client = HelloClient::new(server.channel().await?);
+
+        async fn hello_dan(client: &mut HelloClient<Channel>) -> Result<String, tonic::Status> {
+            let response = client
+                .hello_unary(HelloRequest { name: "Dan".into() })
+                .await?;
+            Ok(response.into_inner().message)
+        }
+        assert_eq!(hello_dan(&mut client).await?, "Hello Dan!");
+        let paul = GrpcMethod::new("example.Hello", "HelloUnary")?;
+        server.update_mocks(|mocks| {
+            mocks.insert(
+                paul,
+                Mock::unary(
+                    HelloRequest {
+                        name: "Paul".into(),
+                    },
+                    HelloResponse {
+                        message: "Hello Paul!".into(),
+                    },
+                ),
+            )
+        })?;
+        let response = client
+            .hello_unary(HelloRequest {
+                name: "Paul".into(),
+            })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Paul!");
+
+        // Changed files are reloaded, replacing mocks added at runtime
+        let yaml = std::fs::read_to_string(&path)?.replace("Hello Dan!", "Hi Dan!");
+        std::fs::write(&path, yaml)?;
+        eventually(|| {
+            let mut client = client.clone();
+            async move { Ok(hello_dan(&mut client).await? == "Hi Dan!") }
+        })
+        .await?;
+        let result = client
+            .hello_unary(HelloRequest {
+                name: "Paul".into(),
+            })
+            .await;
+        assert!(result.is_err_and(|status| status.code() == Code::NotFound));
+
+        // Invalid files are not loaded, keeping the current mocks
+        std::fs::write(
+            &path,
+            "service: example.Hello\nmethod: HelloUnary\nmocks: invalid\n",
+        )?;
+        eventually(|| async { Ok(failures.load(Ordering::SeqCst) > 0) }).await?;
+        assert_eq!(hello_dan(&mut client).await?, "Hi Dan!");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_parallel_servers() -> Result<(), anyhow::Error> {
+        let servers =
+            futures::future::try_join_all((0..32).map(|_| MockHelloServer::start(MockSet::new())))
+                .await?;
+        let mut ports = servers
+            .iter()
+            .map(|server| server.addr().port())
+            .collect::<Vec<_>>();
+        ports.sort();
+        ports.dedup();
+        assert_eq!(ports.len(), servers.len());
+        assert!(ports.iter().all(|&port| port != 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_builder() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        mocks
+            .insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/client_streaming.yaml")?;
+        let builder = MockServer::builder()
+            .ip(Ipv4Addr::LOCALHOST)
+            .tcp_nodelay(true)
+            .http2_keepalive_interval(Duration::from_secs(10))
+            .http2_keepalive_timeout(Duration::from_secs(5))
+            .max_concurrent_streams(16)
+            .max_frame_size(32 * 1024)
+            .max_message_size(16);
+        let server = MockHelloServer::start_with(builder, mocks).await?;
+        assert!(server.addr().ip().is_loopback() && server.addr().is_ipv4());
+
+        let channel = Channel::from_shared(format!("http://127.0.0.1:{}", server.addr().port()))?
+            .connect()
+            .await?;
+        let mut client = HelloClient::new(channel);
+
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        let response = client
+            .hello_unary(HelloRequest {
+                name: "Dan".repeat(64),
+            })
+            .await;
+        assert!(response.is_err_and(|e| e.code() == Code::ResourceExhausted));
+
+        // The limit applies to each message of a stream, not the whole stream
+        let request_stream = futures::stream::iter(
+            ["Dan", "Gaurav", "Paul"].map(|name| HelloRequest { name: name.into() }),
+        );
+        let response = client.hello_client_streaming(request_stream).await?;
+        assert_eq!(
+            response.into_inner().message,
+            "Hello Dan, Gaurav, and Paul!"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_ipv6() -> Result<(), anyhow::Error> {
+        // Skip on hosts without IPv6
+        if std::net::TcpListener::bind((Ipv6Addr::LOCALHOST, 0)).is_err() {
+            return Ok(());
+        }
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let builder = MockServer::builder().ip(Ipv6Addr::LOCALHOST);
+        let server = MockHelloServer::start_with(builder, mocks).await?;
+        assert!(server.addr().ip().is_loopback() && server.addr().is_ipv6());
+
+        let channel = Channel::from_shared(format!("http://[::1]:{}", server.addr().port()))?
+            .connect()
+            .await?;
+        let response = HelloClient::new(channel)
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_uds() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server = MockHelloServer::start_with(MockServer::builder().uds(), mocks).await?;
+        let uds_path = server.uds_path().unwrap().to_path_buf();
+        assert!(uds_path.exists());
+
+        // The URI is ignored by the connector
+        let channel = Endpoint::try_from("http://[::]:50051")?
+            .connect_with_connector(service_fn(move |_: Uri| {
+                let uds_path = uds_path.clone();
+                async move {
+                    Ok::<_, std::io::Error>(TokioIo::new(UnixStream::connect(uds_path).await?))
+                }
+            }))
+            .await?;
+        let mut client = HelloClient::new(channel);
+
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_in_memory() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        mocks
+            .insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/server_streaming.yaml")?;
+        let server = MockHelloServer::start_with(MockServer::builder().in_memory(), mocks).await?;
+        let mut client = HelloClient::new(server.channel().await?);
+
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        let response = client
+            .hello_server_streaming(HelloRequest {
+                name: "Dan, Paul, Gaurav".into(),
+            })
+            .await?;
+        let messages = response
+            .into_inner()
+            .map_ok(|response| response.message)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(messages, ["Hello Dan!", "Hello Paul!", "Hello Gaurav!"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_tls() -> Result<(), anyhow::Error> {
+        let ca = TestCa::generate()?;
+        let server_cert = ca.server_cert(["localhost"])?;
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server = MockHelloServer::start_with(
+            MockServer::builder().tls(&server_cert.cert, &server_cert.key),
+            mocks,
+        )
+        .await?;
+
+        // Plaintext clients are rejected
+        let mut client = HelloClient::new(server.channel().await?);
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await;
+        // The server's TLS handshake is a protocol error to the client, rather than a status
+        let error = response.unwrap_err();
+        assert_eq!(error.code(), Code::Unknown);
+        assert!(std::error::Error::source(&error)
+            .is_some_and(|source| source.is::<tonic::transport::Error>()));
+
+        let tls = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(ca.cert()))
+            .domain_name("localhost");
+        let mut client = HelloClient::new(server.channel_with_tls(tls).await?);
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+        assert!(server.journal()[0].client_cert().is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_mtls() -> Result<(), anyhow::Error> {
+        let ca = TestCa::generate()?;
+        let server_cert = ca.server_cert(["localhost"])?;
+        let client_cert = ca.client_cert("hello-client")?;
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server = MockHelloServer::start_with(
+            MockServer::builder()
+                .in_memory()
+                .tls(&server_cert.cert, &server_cert.key)
+                .tls_client_ca(ca.cert()),
+            mocks,
+        )
+        .await?;
+
+        // Clients without a certificate are rejected
+        let tls = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(ca.cert()))
+            .domain_name("localhost");
+        let channel = server.channel_with_tls(tls.clone()).await?;
+        let response = HelloClient::new(channel)
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await;
+        // The server closes the connection after the handshake
+        assert!(response.is_err_and(|e| matches!(e.code(), Code::Cancelled | Code::Unavailable)));
+        assert!(server.journal().is_empty());
+
+        let tls = tls.identity(Identity::from_pem(&client_cert.cert, &client_cert.key));
+        let mut client = HelloClient::new(server.channel_with_tls(tls).await?);
+        let response = client
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await?;
+        assert_eq!(response.into_inner().message, "Hello Dan!");
+
+        let journal = server.journal();
+        assert_eq!(journal.len(), 1);
+        assert!(journal[0].matched);
+        assert_eq!(journal[0].client_cert(), Some(&client_cert.cert_der));
+        assert_eq!(
+            journal[0].client_common_name().as_deref(),
+            Some("hello-client")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_reflection() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server = MockHelloServer::start_with(
+            MockServer::builder()
+                .in_memory()
+                .reflection(crate::FILE_DESCRIPTOR_SET),
+            mocks,
+        )
+        .await?;
+        let mut client = ServerReflectionClient::new(server.channel().await?);
+
+        let requests = [
+            MessageRequest::ListServices(String::new()),
+            MessageRequest::FileContainingSymbol("example.Hello".into()),
+        ]
+        .map(|message_request| ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(message_request),
+        });
+        let mut responses = client
+            .server_reflection_info(futures::stream::iter(requests))
+            .await?
+            .into_inner();
+
+        let Some(MessageResponse::ListServicesResponse(response)) =
+            responses.message().await?.unwrap().message_response
+        else {
+            panic!("expected list services response");
+        };
+        let mut services = response
+            .service
+            .into_iter()
+            .map(|service| service.name)
+            .collect::<Vec<_>>();
+        services.sort();
+        assert_eq!(
+            services,
+            ["example.Hello", "grpc.reflection.v1.ServerReflection"]
+        );
+
+        let Some(MessageResponse::FileDescriptorResponse(response)) =
+            responses.message().await?.unwrap().message_response
+        else {
+            panic!("expected file descriptor response");
+        };
+        let file = prost_types::FileDescriptorProto::decode(&*response.file_descriptor_proto[0])?;
+        assert_eq!(file.name(), "example.proto");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_address_in_use() -> Result<(), anyhow::Error> {
+        let server = MockHelloServer::start_with(
+            MockServer::builder().ip(Ipv4Addr::LOCALHOST),
+            MockSet::new(),
+        )
+        .await?;
+        let result =
+            MockHelloServer::start_with(MockServer::builder().addr(server.addr()), MockSet::new())
+                .await;
+        assert!(result.is_err_and(|error| matches!(error, grpcmock::Error::IoError(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_invalid_requests() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<HelloRequest, HelloResponse>("stubs/hello/unary.yaml")?;
+        let server = MockServer::new("example.Hello", mocks)?;
+        let grpc_status = |response: &http::Response<_>| {
+            response.headers()["grpc-status"]
+                .to_str()
+                .unwrap()
+                .parse::<i32>()
+                .map(Code::from)
+                .unwrap()
+        };
+
+        // Malformed paths and lowercase method names are unimplemented
+        for path in ["/", "/example.Hello", "/example.Hello/helloUnary", "/a/b/c"] {
+            let request = http::Request::post(path).body(tonic::body::empty_body())?;
+            let response = server.clone().oneshot(request).await?;
+            assert_eq!(grpc_status(&response), Code::Unimplemented, "{path}");
+        }
+
+        // Aborted request bodies are cancelled
+        let body = StreamBody::new(futures::stream::iter([Err::<Frame<Bytes>, _>(
+            tonic::Status::cancelled("client cancelled"),
+        )]));
+        let request = http::Request::post("/example.Hello/HelloUnary").body(BoxBody::new(body))?;
+        let response = server.clone().oneshot(request).await?;
+        assert_eq!(grpc_status(&response), Code::Cancelled);
+
+        // Other request body errors are internal
+        let body = StreamBody::new(futures::stream::iter([Err::<Frame<Bytes>, _>(
+            std::io::Error::other("connection reset"),
+        )]));
+        let request =
+            http::Request::post("/example.Hello/HelloUnary").body(tonic::body::boxed(body))?;
+        let response = server.clone().oneshot(request).await?;
+        assert_eq!(grpc_status(&response), Code::Internal);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hello_with_multiline_error() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        mocks.insert(
+            GrpcMethod::new("example.Hello", "HelloUnary")?,
+            Mock::unary(
+                HelloRequest { name: "Dan".into() },
+                HelloResponse::default(),
+            )
+            .with_code(http::StatusCode::BAD_REQUEST)
+            .with_error("invalid name:\n\"Dan\""),
+        );
+        let server = MockHelloServer::start_with(MockServer::builder().in_memory(), mocks).await?;
+        let response = HelloClient::new(server.channel().await?)
+            .hello_unary(HelloRequest { name: "Dan".into() })
+            .await;
+        assert!(response.is_err_and(|e| e.message() == "invalid name:\n\"Dan\""));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_hello_with_invalid_mocks() {
         let mut mocks = MockSet::new();
@@ -80,4 +1161,56 @@ mod tests {
             error.to_string() == "invalid: all mocks must be for `example.Hello` service"
         }))
     }
+
+    #[tokio::test]
+    async fn test_hello_with_mismatched_method_kind() -> Result<(), anyhow::Error> {
+        // Mock files are validated against the method's kind when loaded
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let result = MockSet::new().insert_from_file_with_descriptors(
+            "stubs/invalid/unary_stream_response.yaml",
+            &descriptors,
+        );
+        assert!(result.is_err_and(|error| {
+            error.to_string()
+                == "invalid: mock for unary method `/example.Hello/HelloUnary` has a stream response body"
+        }));
+        let result = MockSet::new().insert_from_file::<HelloRequest, HelloResponse>(
+            "stubs/invalid/unary_stream_response.yaml",
+        );
+        assert!(result.is_err());
+
+        // Mocks created in Rust are validated against the method's kind when the server is built
+        let method = GrpcMethod::new("example.Hello", "HelloUnary")?;
+        let mut mocks = MockSet::new();
+        mocks.insert(
+            method.clone(),
+            Mock::unary(
+                HelloRequest { name: "Dan".into() },
+                HelloResponse {
+                    message: "Hello Dan!".into(),
+                },
+            ),
+        );
+        mocks.insert(
+            method,
+            Mock::server_streaming(
+                HelloRequest {
+                    name: "Paul".into(),
+                },
+                [HelloResponse {
+                    message: "Hello Paul!".into(),
+                }],
+            ),
+        );
+        assert_eq!(
+            mocks.keys().next().and_then(GrpcMethod::kind),
+            Some(MethodKind::Unary)
+        );
+        assert!(MockHelloServer::start(mocks).await.is_err_and(|error| {
+            error.to_string()
+                == "invalid: mock for unary method `/example.Hello/HelloUnary` is server-streaming"
+        }));
+
+        Ok(())
+    }
 }
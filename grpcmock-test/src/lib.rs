@@ -1,3 +1,23 @@
 mod health;
 mod hello;
 mod tgis;
+mod types;
+
+pub(crate) const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/descriptor.bin"));
+
+/// Polls `check` until it returns `true`, failing if it doesn't within a few seconds.
+#[cfg(test)]
+pub(crate) async fn eventually<F, Fut>(mut check: F) -> Result<(), anyhow::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<bool, anyhow::Error>>,
+{
+    let poll = async {
+        while !check().await? {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        Ok(())
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(10), poll).await?
+}
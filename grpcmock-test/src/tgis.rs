@@ -6,7 +6,8 @@ mod pb {
 mod tests {
     use super::pb::{
         generation_service_client::GenerationServiceClient, BatchedGenerationRequest,
-        BatchedGenerationResponse, GenerationRequest, GenerationResponse,
+        BatchedGenerationResponse, DecodingMethod, GenerationRequest, GenerationResponse,
+        Parameters, SamplingParameters, StopReason,
     };
     use grpcmock::prelude::*;
     use tonic::{transport::Channel, Code};
@@ -56,6 +57,102 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_generate_with_descriptors() -> Result<(), anyhow::Error> {
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file_with_descriptors("stubs/tgis/generate.yaml", &descriptors)?;
+        let server = MockGenerationServer::start(mocks).await?;
+
+        let mut client = GenerationServiceClient::new(server.channel().await?);
+        let response = client
+            .generate(BatchedGenerationRequest {
+                model_id: "bloom-560m".into(),
+                prefix_id: None,
+                requests: vec![GenerationRequest {
+                    text: "What's up?".into(),
+                }],
+                params: None,
+            })
+            .await?;
+        assert_eq!(response.into_inner().responses[0].text, "Not much, you?");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_canonical_json() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        // Enum names, int64 strings, camelCase names and omitted fields
+        mocks.insert_from_file::<BatchedGenerationRequest, BatchedGenerationResponse>(
+            "stubs/tgis/generate_canonical.yaml",
+        )?;
+        let server = MockGenerationServer::start(mocks).await?;
+
+        let mut client = GenerationServiceClient::new(server.channel().await?);
+        let response = client
+            .generate(BatchedGenerationRequest {
+                model_id: "bloom-560m".into(),
+                prefix_id: None,
+                requests: vec![GenerationRequest {
+                    text: "What's up?".into(),
+                }],
+                params: Some(Parameters {
+                    method: DecodingMethod::Sample.into(),
+                    sampling: Some(SamplingParameters {
+                        top_k: 5,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+            })
+            .await?;
+        assert_eq!(
+            response.into_inner().responses,
+            [GenerationResponse {
+                generated_token_count: 12,
+                text: "Not much, you?".into(),
+                stop_reason: StopReason::MaxTokens.into(),
+                seed: 42,
+                ..Default::default()
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_native_yaml() -> Result<(), anyhow::Error> {
+        let mut mocks = MockSet::new();
+        // Bodies written as YAML mappings, with an int64 number
+        mocks.insert_from_file::<BatchedGenerationRequest, BatchedGenerationResponse>(
+            "stubs/tgis/generate_native.yaml",
+        )?;
+        let server = MockGenerationServer::start(mocks).await?;
+
+        let mut client = GenerationServiceClient::new(server.channel().await?);
+        let response = client
+            .generate(BatchedGenerationRequest {
+                model_id: "bloom-560m".into(),
+                prefix_id: None,
+                requests: vec![GenerationRequest {
+                    text: "What's up?".into(),
+                }],
+                params: Some(Parameters {
+                    method: DecodingMethod::Sample.into(),
+                    sampling: Some(SamplingParameters {
+                        top_k: 5,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+            })
+            .await?;
+        assert_eq!(response.into_inner().responses[0].seed, 42);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_generate() -> Result<(), anyhow::Error> {
         let mocks = MockSet::from_iter([(
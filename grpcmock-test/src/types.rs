@@ -0,0 +1,127 @@
+mod pb {
+    tonic::include_proto!("types");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pb::{events_client::EventsClient, Event};
+    use crate::hello::pb::{HelloRequest, HelloResponse};
+    use grpcmock::{
+        mock::{MockFile, MockFormat},
+        prelude::*,
+    };
+    use prost::Message;
+    use prost_types::{value::Kind, Any, Duration, ListValue, Struct, Timestamp, Value};
+
+    grpcmock::generate_server!("types.Events", MockEventsServer);
+
+    fn value(kind: Kind) -> Value {
+        Value { kind: Some(kind) }
+    }
+
+    fn event() -> Event {
+        Event {
+            time: Some(Timestamp {
+                seconds: 1704164645,
+                nanos: 500_000_000,
+            }),
+            duration: Some(Duration {
+                seconds: 1,
+                nanos: 500_000_000,
+            }),
+            attributes: Some(Struct {
+                fields: [
+                    ("source".into(), value(Kind::StringValue("test".into()))),
+                    ("retries".into(), value(Kind::NumberValue(3.0))),
+                    (
+                        "tags".into(),
+                        value(Kind::ListValue(ListValue {
+                            values: vec![
+                                value(Kind::StringValue("a".into())),
+                                value(Kind::StringValue("b".into())),
+                            ],
+                        })),
+                    ),
+                    ("parent".into(), value(Kind::NullValue(0))),
+                ]
+                .into(),
+            }),
+            payload: Some(Any {
+                type_url: "type.googleapis.com/example.HelloRequest".into(),
+                value: HelloRequest { name: "Dan".into() }.encode_to_vec(),
+            }),
+        }
+    }
+
+    fn assert_response(response: Event) -> Result<(), anyhow::Error> {
+        assert_eq!(
+            response.time,
+            Some(Timestamp {
+                seconds: 1704164646,
+                nanos: 0,
+            })
+        );
+        assert_eq!(
+            response.duration,
+            Some(Duration {
+                seconds: 0,
+                nanos: 250_000_000,
+            })
+        );
+        assert_eq!(
+            response.attributes.unwrap().fields["status"],
+            value(Kind::StringValue("created".into()))
+        );
+        let payload = response.payload.unwrap();
+        assert_eq!(
+            payload.type_url,
+            "type.googleapis.com/example.HelloResponse"
+        );
+        assert_eq!(
+            HelloResponse::decode(payload.value.as_slice())?.message,
+            "Hello Dan!"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_create_event_with_well_known_types() -> Result<(), anyhow::Error> {
+        // Timestamp, Duration, Struct and Any bodies use their special JSON forms
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file::<Event, Event>("stubs/types/create_event.yaml")?;
+        let server = MockEventsServer::start(mocks).await?;
+        let mut client = EventsClient::new(server.channel().await?);
+        let response = client.create_event(event()).await?;
+        assert_response(response.into_inner())?;
+
+        // Also when converted using descriptors
+        let descriptors = DescriptorPool::decode(crate::FILE_DESCRIPTOR_SET)?;
+        let mut mocks = MockSet::new();
+        mocks.insert_from_file_with_descriptors("stubs/types/create_event.yaml", &descriptors)?;
+        let server = MockEventsServer::start(mocks).await?;
+        let mut client = EventsClient::new(server.channel().await?);
+        let response = client.create_event(event()).await?;
+        assert_response(response.into_inner())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_any_with_unknown_type() -> Result<(), anyhow::Error> {
+        // Any type URLs are resolved through the descriptor pool of the message types
+        let yaml = r#"
+service: types.Events
+method: CreateEvent
+mocks:
+  - request:
+      body: '{"payload": {"@type": "type.googleapis.com/example.Unknown", "name": "Dan"}}'
+    response:
+      body: '{}'
+"#;
+        let file = MockFile::parse_str(yaml, MockFormat::Yaml)?.remove(0);
+        let result = file.into_mocks::<Event, Event>();
+        assert!(result.is_err_and(|error| error.to_string().contains("example.Unknown")));
+
+        Ok(())
+    }
+}
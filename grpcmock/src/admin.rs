@@ -0,0 +1,188 @@
+//! An HTTP/JSON admin API to manage a [`MockServer`]'s mocks and journal at runtime.
+//!
+//! | Route | Description |
+//! | --- | --- |
+//! | `GET /mocks` | Lists mocks as mock file sections |
+//! | `POST /mocks` | Adds mocks from a mock file section, or a list of sections |
+//! | `DELETE /mocks` | Removes all mocks |
+//! | `DELETE /mocks/{service}/{method}` | Removes mocks for a method |
+//! | `POST /mocks/reset` | Resets mocks to those the server was built with |
+//! | `GET /journal` | Lists requests received by the server |
+//! | `DELETE /journal` | Clears the journal |
+//! | `POST /reset` | Resets mocks and clears the journal |
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use http::HeaderMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    journal::JournalEntry,
+    method::GrpcMethod,
+    mock::{JsonMockBody, MockBody, MockFile},
+    registry::TypeResolver,
+    server::MockServer,
+    Error,
+};
+
+/// Creates an admin API [`Router`] for `server`, using `descriptors` to convert bodies to and from JSON.
+///
+/// Serve it on a separate address, e.g. with [`axum::serve()`].
+pub fn router(
+    server: MockServer,
+    descriptors: impl TypeResolver + Send + Sync + 'static,
+) -> Router {
+    let state = AdminState {
+        server,
+        descriptors: Arc::new(descriptors),
+    };
+    Router::new()
+        .route(
+            "/mocks",
+            get(list_mocks).post(add_mocks).delete(clear_mocks),
+        )
+        .route("/mocks/reset", post(reset_mocks))
+        .route("/mocks/:service/:method", delete(remove_mocks))
+        .route("/journal", get(list_journal).delete(clear_journal))
+        .route("/reset", post(reset))
+        .with_state(state)
+}
+
+#[derive(Clone)]
+struct AdminState {
+    server: MockServer,
+    descriptors: Arc<dyn TypeResolver + Send + Sync>,
+}
+
+/// One or more mock file sections.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MockFiles {
+    Single(MockFile),
+    Multiple(Vec<MockFile>),
+}
+
+/// A request received by the server, with its body converted to JSON if possible.
+#[derive(Serialize)]
+struct JournalEntryJson {
+    service: String,
+    method: String,
+    #[serde(with = "http_serde::header_map")]
+    headers: HeaderMap,
+    body: Option<JsonMockBody>,
+    matched: bool,
+    proxied: bool,
+}
+
+/// An admin API error response.
+struct AdminError(StatusCode, String);
+
+impl From<Error> for AdminError {
+    fn from(error: Error) -> Self {
+        Self(StatusCode::BAD_REQUEST, error.to_string())
+    }
+}
+
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        let Self(code, error) = self;
+        (code, Json(serde_json::json!({ "error": error }))).into_response()
+    }
+}
+
+async fn list_mocks(State(state): State<AdminState>) -> Result<Json<Vec<MockFile>>, AdminError> {
+    let files = state
+        .server
+        .mocks()
+        .to_mock_files(state.descriptors.as_ref())?;
+    Ok(Json(files))
+}
+
+async fn add_mocks(
+    State(state): State<AdminState>,
+    Json(files): Json<MockFiles>,
+) -> Result<StatusCode, AdminError> {
+    let files = match files {
+        MockFiles::Single(file) => vec![file],
+        MockFiles::Multiple(files) => files,
+    };
+    let mocks = files
+        .into_iter()
+        .map(|file| file.into_mocks_with_descriptors(state.descriptors.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+    state.server.update_mocks(|current| current.extend(mocks))?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn clear_mocks(State(state): State<AdminState>) -> Result<StatusCode, AdminError> {
+    state.server.update_mocks(|mocks| mocks.clear())?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn remove_mocks(
+    State(state): State<AdminState>,
+    Path((service, method)): Path<(String, String)>,
+) -> Result<StatusCode, AdminError> {
+    let method = GrpcMethod::new(service, method)?;
+    let mut removed = false;
+    state
+        .server
+        .update_mocks(|mocks| removed = mocks.remove(&method).is_some())?;
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AdminError(
+            StatusCode::NOT_FOUND,
+            format!("method `{method}` has no mocks"),
+        ))
+    }
+}
+
+async fn reset_mocks(State(state): State<AdminState>) -> StatusCode {
+    state.server.reset_mocks();
+    StatusCode::NO_CONTENT
+}
+
+async fn list_journal(State(state): State<AdminState>) -> Json<Vec<JournalEntryJson>> {
+    let entries = state
+        .server
+        .journal()
+        .into_iter()
+        .map(|entry| journal_entry_json(entry, state.descriptors.as_ref()))
+        .collect();
+    Json(entries)
+}
+
+async fn clear_journal(State(state): State<AdminState>) -> StatusCode {
+    state.server.reset_journal();
+    StatusCode::NO_CONTENT
+}
+
+async fn reset(State(state): State<AdminState>) -> StatusCode {
+    state.server.reset_mocks();
+    state.server.reset_journal();
+    StatusCode::NO_CONTENT
+}
+
+fn journal_entry_json(entry: JournalEntry, descriptors: &dyn TypeResolver) -> JournalEntryJson {
+    let body = descriptors.resolve(&entry.method).ok().and_then(|types| {
+        let streaming = types.kind.is_some_and(|kind| kind.is_client_streaming());
+        MockBody::Full(entry.body)
+            .to_json(&types.input, streaming)
+            .ok()
+    });
+    JournalEntryJson {
+        service: entry.method.service().to_string(),
+        method: entry.method.name().to_string(),
+        headers: entry.headers,
+        body,
+        matched: entry.matched,
+        proxied: entry.proxied,
+    }
+}
@@ -0,0 +1,154 @@
+//! A standalone gRPC mock server, serving mocks from mock files.
+use std::{net::SocketAddr, path::PathBuf};
+
+use clap::{ArgGroup, Parser};
+use grpcmock::prelude::*;
+use tonic::transport::{Endpoint, Uri};
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+
+/// Serves gRPC mocks defined in mock files.
+#[derive(Debug, Parser)]
+#[command(name = "grpcmock", version, about)]
+#[command(group(
+    ArgGroup::new("descriptors")
+        .required(true)
+        .multiple(true)
+        .args(["descriptor_sets", "protos"])
+))]
+struct Args {
+    /// Encoded `FileDescriptorSet` describing mocked services
+    #[arg(long = "descriptor-set", value_name = "FILE")]
+    descriptor_sets: Vec<PathBuf>,
+    /// Proto file describing mocked services
+    #[arg(long = "proto", value_name = "FILE")]
+    protos: Vec<PathBuf>,
+    /// Include directory to resolve proto files and imports, defaults to the current directory
+    #[arg(short = 'I', long = "include", value_name = "DIR")]
+    includes: Vec<PathBuf>,
+    /// Directory of mock files
+    #[arg(long, value_name = "DIR", required = true)]
+    stubs: Vec<PathBuf>,
+    /// Glob pattern of mock file paths to load, relative to stub directories; files without a
+    /// mock file extension (`.yaml`, `.yml`, `.json` or `.toml`) are skipped
+    #[arg(long, default_value = "**/*")]
+    pattern: String,
+    /// Address to listen on
+    #[arg(long, default_value = "0.0.0.0:50051")]
+    addr: SocketAddr,
+    /// Serve the `grpc.health.v1.Health` service
+    #[arg(long)]
+    health: bool,
+    /// Reload mocks when files in stub directories change
+    #[arg(long)]
+    watch: bool,
+    /// Address to serve the HTTP/JSON admin API on, disabled if not set
+    #[arg(long, value_name = "ADDR")]
+    admin_addr: Option<SocketAddr>,
+    /// Forward all requests to an upstream server, e.g. `http://localhost:50052`, recording
+    /// responses as mock files in the first stub directory
+    #[arg(long, value_name = "URL")]
+    record: Option<Uri>,
+    /// Forward requests for described services that don't match any mock to an upstream server,
+    /// e.g. `http://localhost:50052`
+    #[arg(long, value_name = "URL", conflicts_with = "record")]
+    passthrough: Option<Uri>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .init();
+    let args = Args::parse();
+
+    let mut descriptors = DescriptorPool::new();
+    for path in &args.descriptor_sets {
+        descriptors.decode_file_descriptor_set(std::fs::read(path)?.as_slice())?;
+    }
+    if !args.protos.is_empty() {
+        let includes = if args.includes.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            args.includes
+        };
+        descriptors.add_file_descriptor_set(protox::compile(&args.protos, &includes)?)?;
+    }
+
+    let mocks = load_mocks(&args.stubs, &args.pattern, &descriptors)?;
+    info!(methods = mocks.len(), "loaded mocks");
+
+    let mut builder = MockServer::builder()
+        .addr(args.addr)
+        .reflection(descriptors.encode_to_vec());
+    if args.health {
+        builder = builder.health();
+    }
+    if let Some(upstream) = args.record {
+        info!(%upstream, "recording mocks");
+        let channel = Endpoint::from(upstream).connect_lazy();
+        builder = builder.record(channel, &args.stubs[0], descriptors.clone());
+    }
+    if let Some(upstream) = args.passthrough {
+        info!(%upstream, "forwarding unmatched requests");
+        builder = builder.passthrough(Endpoint::from(upstream).connect_lazy());
+        // Requests for described services are forwarded, including services without mocks
+        for service in descriptors.services() {
+            builder = builder.service(service.full_name());
+        }
+    }
+    let server = builder.start(mocks).await?;
+    info!("listening on {}", server.addr());
+
+    let _watcher = if args.watch {
+        let (stubs, pattern, descriptors) = (args.stubs.clone(), args.pattern, descriptors.clone());
+        Some(server.watch(&args.stubs, move || {
+            load_mocks(&stubs, &pattern, &descriptors)
+        })?)
+    } else {
+        None
+    };
+
+    if let Some(addr) = args.admin_addr {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        info!("admin API listening on {}", listener.local_addr()?);
+        let router = grpcmock::admin::router(server.clone(), descriptors);
+        tokio::spawn(async move {
+            if let Err(error) = axum::serve(listener, router).await {
+                error!(%error, "admin API failed");
+            }
+        });
+    }
+
+    shutdown_signal().await?;
+    info!("shutting down");
+    Ok(())
+}
+
+/// Loads mocks from all mock files in `stubs` directories.
+fn load_mocks(
+    stubs: &[PathBuf],
+    pattern: &str,
+    descriptors: &DescriptorPool,
+) -> Result<MockSet, grpcmock::Error> {
+    let mut mocks = MockSet::new();
+    for dir in stubs {
+        mocks.load_dir(dir, pattern, descriptors)?;
+    }
+    Ok(mocks)
+}
+
+/// Waits for SIGINT, or SIGTERM on Unix, e.g. from `docker stop`.
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
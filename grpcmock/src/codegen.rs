@@ -1,7 +1,16 @@
 /// Generates a mock gRPC server.
+///
+/// A server can host multiple services, e.g. `generate_server!(["example.Hello", "grpc.health.v1.Health"], MockServer)`.
 #[macro_export]
 macro_rules! generate_server {
     ($name:literal, $type:ident) => {
+        $crate::generate_server!([$name], $type);
+
+        impl tonic::server::NamedService for $type {
+            const NAME: &'static str = $name;
+        }
+    };
+    ([$($name:literal),+ $(,)?], $type:ident) => {
         #[derive(Clone)]
         pub struct $type($crate::server::MockServer);
 
@@ -40,24 +49,23 @@ macro_rules! generate_server {
             }
         }
 
-        impl tonic::server::NamedService for $type {
-            const NAME: &'static str = $name;
-        }
-
         impl $type {
             pub async fn start(mocks: $crate::mock::MockSet) -> Result<Self, $crate::Error> {
-                let server = $crate::server::MockServer::new($name, mocks)?;
-                Ok(Self(server).serve().await)
+                Self::start_with($crate::server::MockServer::builder(), mocks).await
+            }
+
+            pub async fn start_with(
+                builder: $crate::server::MockServerBuilder,
+                mocks: $crate::mock::MockSet,
+            ) -> Result<Self, $crate::Error> {
+                let server = builder$(.service($name))+.build(mocks)?;
+                Self(server).serve().await
             }
 
-            async fn serve(&mut self) -> Self {
-                let handle = tokio::spawn(
-                    tonic::transport::Server::builder()
-                        .add_service(self.clone())
-                        .serve(self.addr()),
-                );
-                self._start(handle).await;
-                self.to_owned()
+            async fn serve(&mut self) -> Result<Self, $crate::Error> {
+                let service = self.clone();
+                self._serve(service).await?;
+                Ok(self.to_owned())
             }
         }
     };
@@ -0,0 +1,240 @@
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use http::HeaderMap;
+
+use crate::method::GrpcMethod;
+
+/// A journal of requests received by a mock server.
+#[derive(Default, Debug)]
+pub(crate) struct Journal(Mutex<Vec<JournalEntry>>);
+
+impl Journal {
+    /// Records a request.
+    pub fn push(&self, entry: JournalEntry) {
+        self.0.lock().unwrap().push(entry);
+    }
+
+    /// Returns recorded requests in the order they were received.
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Removes all recorded requests.
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// A request received by a mock server.
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    pub method: GrpcMethod,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    /// Whether the request matched a mock.
+    pub matched: bool,
+    /// Whether the request was forwarded to a passthrough upstream server, see [`MockServerBuilder::passthrough`].
+    ///
+    /// Requests forwarded while recording, see [`MockServerBuilder::record`], are not marked as proxied.
+    ///
+    /// [`MockServerBuilder::passthrough`]: crate::server::MockServerBuilder::passthrough
+    /// [`MockServerBuilder::record`]: crate::server::MockServerBuilder::record
+    pub proxied: bool,
+    /// DER-encoded certificate chain presented by the client, empty unless using mutual TLS.
+    pub client_certs: Vec<Bytes>,
+}
+
+impl JournalEntry {
+    /// Returns the client's DER-encoded end-entity certificate, if presented.
+    pub fn client_cert(&self) -> Option<&Bytes> {
+        self.client_certs.first()
+    }
+
+    /// Returns the subject common name (CN) of the client's certificate, if presented.
+    pub fn client_common_name(&self) -> Option<String> {
+        subject_common_name(self.client_cert()?)
+    }
+}
+
+/// Reads a DER element, returning its tag, contents and the remaining input.
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&len, rest) = rest.split_first()?;
+    let (len, rest) = if len & 0x80 == 0 {
+        (len as usize, rest)
+    } else {
+        // Long form, the low bits are the number of length bytes
+        let n = (len & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n]
+            .iter()
+            .fold(0, |len, &byte| len << 8 | byte as usize);
+        (len, &rest[n..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+/// Returns the subject common name of a DER-encoded X.509 certificate.
+fn subject_common_name(cert: &[u8]) -> Option<String> {
+    // OID 2.5.4.3
+    const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+    const VERSION: u8 = 0xa0;
+
+    let (_, cert, _) = der_element(cert)?;
+    let (_, tbs_cert, _) = der_element(cert)?;
+    // Skip the optional version and serial number, then signature algorithm, issuer and validity
+    let (tag, _, mut rest) = der_element(tbs_cert)?;
+    if tag == VERSION {
+        rest = der_element(rest)?.2;
+    }
+    for _ in 0..3 {
+        rest = der_element(rest)?.2;
+    }
+    // The subject is a sequence of sets of (type, value) attributes
+    let (_, mut names, _) = der_element(rest)?;
+    while !names.is_empty() {
+        let (_, mut attributes, next) = der_element(names)?;
+        names = next;
+        while !attributes.is_empty() {
+            let (_, attribute, next) = der_element(attributes)?;
+            attributes = next;
+            let (_, oid, value) = der_element(attribute)?;
+            if oid == COMMON_NAME {
+                let (_, value, _) = der_element(value)?;
+                return String::from_utf8(value.to_vec()).ok();
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes a DER element, using the long length form for contents of 128 bytes or more.
+    fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+        match contents.len() {
+            len @ 0..=0x7f => element.push(len as u8),
+            len @ 0x80..=0xff => element.extend([0x81, len as u8]),
+            len => element.extend([0x82, (len >> 8) as u8, len as u8]),
+        }
+        element.extend_from_slice(contents);
+        element
+    }
+
+    fn sequence(elements: &[Vec<u8>]) -> Vec<u8> {
+        der(0x30, &elements.concat())
+    }
+
+    /// An attribute with an OID of 2.5.4.`kind` and a UTF8String value.
+    fn attribute(kind: u8, value: &str) -> Vec<u8> {
+        sequence(&[der(0x06, &[0x55, 0x04, kind]), der(0x0c, value.as_bytes())])
+    }
+
+    /// A certificate with the given subject, and a large enough issuer to need long form lengths.
+    fn certificate(subject: &[Vec<u8>]) -> Vec<u8> {
+        let name = |value: &str| sequence(&[der(0x31, &attribute(0x0a, value))]);
+        let tbs_cert = sequence(&[
+            der(0xa0, &der(0x02, &[2])),
+            der(0x02, &[1]),
+            sequence(&[der(0x06, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02])]),
+            name(&"issuer".repeat(50)),
+            sequence(&[der(0x17, b"250101000000Z"), der(0x17, b"350101000000Z")]),
+            sequence(subject),
+        ]);
+        sequence(&[tbs_cert])
+    }
+
+    #[test]
+    fn test_der_element() {
+        assert_eq!(
+            der_element(&[0x02, 0x01, 0x05, 0xff]),
+            Some((0x02, &[0x05][..], &[0xff][..]))
+        );
+        // Long form lengths
+        let contents = [0; 0x100];
+        let element = der(0x04, &contents);
+        assert_eq!(&element[..4], &[0x04, 0x82, 0x01, 0x00]);
+        assert_eq!(der_element(&element), Some((0x04, &contents[..], &[][..])));
+        assert_eq!(
+            der_element(&[0x04, 0x81, 0x01, 0x05]),
+            Some((0x04, &[0x05][..], &[][..]))
+        );
+        // Truncated tags, lengths and contents
+        assert_eq!(der_element(&[]), None);
+        assert_eq!(der_element(&[0x02]), None);
+        assert_eq!(der_element(&[0x04, 0x82, 0x01]), None);
+        assert_eq!(der_element(&element[..element.len() - 1]), None);
+        assert_eq!(der_element(&[0x02, 0x02, 0x05]), None);
+        // Indefinite and overlong lengths
+        assert_eq!(
+            der_element(&[0x30, 0x80, 0x02, 0x01, 0x05, 0x00, 0x00]),
+            None
+        );
+        assert_eq!(der_element(&[0x04, 0x85, 0, 0, 0, 0, 1, 0x05]), None);
+    }
+
+    #[test]
+    fn test_subject_common_name() {
+        let cert = certificate(&[
+            der(0x31, &attribute(0x06, "NZ")),
+            der(0x31, &attribute(0x03, "client")),
+        ]);
+        assert_eq!(subject_common_name(&cert).as_deref(), Some("client"));
+
+        // A multi-valued relative distinguished name
+        let cert = certificate(&[der(
+            0x31,
+            &[attribute(0x0a, "grpcmock"), attribute(0x03, "client")].concat(),
+        )]);
+        assert_eq!(subject_common_name(&cert).as_deref(), Some("client"));
+
+        // Without a version
+        let mut tbs_cert = der_element(der_element(&cert).unwrap().1).unwrap().1;
+        tbs_cert = der_element(tbs_cert).unwrap().2;
+        let cert = sequence(&[der(0x30, tbs_cert)]);
+        assert_eq!(subject_common_name(&cert).as_deref(), Some("client"));
+    }
+
+    #[test]
+    fn test_subject_common_name_without_common_name() {
+        let cert = certificate(&[der(0x31, &attribute(0x0a, "grpcmock"))]);
+        assert_eq!(subject_common_name(&cert), None);
+        let cert = certificate(&[]);
+        assert_eq!(subject_common_name(&cert), None);
+
+        // Malformed certificates
+        assert_eq!(subject_common_name(&[]), None);
+        let cert = certificate(&[der(0x31, &attribute(0x03, "client"))]);
+        assert_eq!(subject_common_name(&cert[..cert.len() - 1]), None);
+        let cert = sequence(&[sequence(&[der(0x02, &[1])])]);
+        assert_eq!(subject_common_name(&cert), None);
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_client_common_name() {
+        let ca = crate::tls::TestCa::generate().unwrap();
+        let client = ca.client_cert("client").unwrap();
+        let server = ca.server_cert(["localhost"]).unwrap();
+        let entry = |client_certs| JournalEntry {
+            method: "/helloworld.Greeter/SayHello".parse().unwrap(),
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+            matched: false,
+            proxied: false,
+            client_certs,
+        };
+        let common_name = entry(vec![client.cert_der, server.cert_der]).client_common_name();
+        assert_eq!(common_name.as_deref(), Some("client"));
+        assert_eq!(entry(vec![]).client_common_name(), None);
+    }
+}
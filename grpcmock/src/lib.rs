@@ -1,16 +1,29 @@
 #![doc = include_str!("../README.md")]
+#[cfg(feature = "admin")]
+pub mod admin;
 pub mod codegen;
+pub mod journal;
 pub mod method;
 pub mod mock;
+mod proxy;
+pub mod registry;
 pub mod server;
+#[cfg(feature = "tls")]
+pub mod tls;
 pub mod utils;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub use prost_reflect;
 pub mod prelude {
     pub use crate::generate_server;
-    pub use crate::method::GrpcMethod;
+    pub use crate::journal::JournalEntry;
+    pub use crate::method::{GrpcMethod, MethodKind};
     pub use crate::mock::{Mock, MockBody, MockRequest, MockResponse, MockSet};
-    pub use crate::server::MockServer;
+    pub use crate::registry::{TypeRegistry, TypeResolver as _};
+    pub use crate::server::{MockServer, MockServerBuilder, ServingStatus};
     pub use crate::utils::prost::MessageExt as _;
     pub use crate::Error;
+    pub use prost_reflect::DescriptorPool;
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -21,6 +34,26 @@ pub enum Error {
     YamlError(#[from] serde_yml::Error),
     #[error("json error: {0}")]
     JsonError(#[from] serde_json::Error),
+    #[error("toml error: {0}")]
+    TomlError(#[from] toml::de::Error),
     #[error("io error: {0}")]
     IoError(#[from] std::io::Error),
+    #[error("decode error: {0}")]
+    DecodeError(#[from] prost::DecodeError),
+    #[error("text format error: {0}")]
+    TextFormatError(#[from] prost_reflect::text_format::ParseError),
+    #[error("base64 error: {0}")]
+    Base64Error(#[from] base64::DecodeError),
+    #[error("descriptor error: {0}")]
+    DescriptorError(#[from] prost_reflect::DescriptorError),
+    #[error("transport error: {0}")]
+    TransportError(#[from] tonic::transport::Error),
+    #[error("server error: {0}")]
+    ServerError(String),
+    #[cfg(feature = "tls")]
+    #[error("tls error: {0}")]
+    TlsError(#[from] rcgen::Error),
+    #[cfg(feature = "watch")]
+    #[error("watch error: {0}")]
+    WatchError(#[from] notify::Error),
 }
@@ -1,12 +1,17 @@
+use prost_reflect::{DescriptorPool, MethodDescriptor};
 use serde::Deserialize;
 
 use crate::Error;
 
 /// A gRPC method.
-#[derive(Debug, Clone, PartialEq, Hash, Eq, Deserialize)]
+///
+/// Methods are compared and hashed by service and name only, [`MethodKind`] is not considered.
+#[derive(Debug, Clone, Deserialize)]
 pub struct GrpcMethod {
     service: String,
     name: String,
+    #[serde(skip)]
+    kind: Option<MethodKind>,
 }
 
 impl GrpcMethod {
@@ -26,7 +31,22 @@ impl GrpcMethod {
         if name.chars().nth(0).is_some_and(|c| !c.is_uppercase()) {
             return Err(Error::Invalid("name should start with uppercase".into()));
         }
-        Ok(Self { service, name })
+        Ok(Self {
+            service,
+            name,
+            kind: None,
+        })
+    }
+
+    /// Sets the method's [`MethodKind`].
+    pub fn with_kind(mut self, kind: MethodKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Returns method's kind, if known.
+    pub fn kind(&self) -> Option<MethodKind> {
+        self.kind
     }
 
     /// Returns method's service name.
@@ -43,6 +63,36 @@ impl GrpcMethod {
     pub fn path(&self) -> String {
         format!("/{}/{}", self.service, self.name)
     }
+
+    /// Looks up the method's descriptor in a [`DescriptorPool`].
+    pub fn descriptor(&self, descriptors: &DescriptorPool) -> Result<MethodDescriptor, Error> {
+        descriptors
+            .get_service_by_name(&self.service)
+            .ok_or_else(|| {
+                Error::Invalid(format!(
+                    "service `{}` not found in descriptors",
+                    self.service
+                ))
+            })?
+            .methods()
+            .find(|method| method.name() == self.name)
+            .ok_or_else(|| Error::Invalid(format!("method `{self}` not found in descriptors")))
+    }
+}
+
+impl PartialEq for GrpcMethod {
+    fn eq(&self, other: &Self) -> bool {
+        self.service == other.service && self.name == other.name
+    }
+}
+
+impl Eq for GrpcMethod {}
+
+impl std::hash::Hash for GrpcMethod {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.service.hash(state);
+        self.name.hash(state);
+    }
 }
 
 impl std::fmt::Display for GrpcMethod {
@@ -78,3 +128,49 @@ impl std::str::FromStr for GrpcMethod {
         }
     }
 }
+
+/// The streaming kind of a gRPC method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MethodKind {
+    Unary,
+    ClientStreaming,
+    ServerStreaming,
+    BidiStreaming,
+}
+
+impl MethodKind {
+    /// Returns `true` if the method's request is a stream.
+    pub fn is_client_streaming(&self) -> bool {
+        matches!(self, Self::ClientStreaming | Self::BidiStreaming)
+    }
+
+    /// Returns `true` if the method's response is a stream.
+    pub fn is_server_streaming(&self) -> bool {
+        matches!(self, Self::ServerStreaming | Self::BidiStreaming)
+    }
+}
+
+impl From<&MethodDescriptor> for MethodKind {
+    fn from(descriptor: &MethodDescriptor) -> Self {
+        match (
+            descriptor.is_client_streaming(),
+            descriptor.is_server_streaming(),
+        ) {
+            (false, false) => Self::Unary,
+            (true, false) => Self::ClientStreaming,
+            (false, true) => Self::ServerStreaming,
+            (true, true) => Self::BidiStreaming,
+        }
+    }
+}
+
+impl std::fmt::Display for MethodKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unary => write!(f, "unary"),
+            Self::ClientStreaming => write!(f, "client-streaming"),
+            Self::ServerStreaming => write!(f, "server-streaming"),
+            Self::BidiStreaming => write!(f, "bidi-streaming"),
+        }
+    }
+}
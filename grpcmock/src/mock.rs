@@ -1,20 +1,26 @@
 use std::{
-    collections::{hash_map, HashMap},
-    fs::File,
-    path::Path,
+    collections::{hash_map, HashMap, HashSet},
+    convert::Infallible,
+    path::{Component, Path, PathBuf},
 };
 
+use base64::{prelude::BASE64_STANDARD, Engine};
 use bytes::Bytes;
 use http::HeaderMap;
 use http_body::Frame;
 use http_body_util::{Full, StreamBody};
 use prost::Message;
-use serde::{de::DeserializeOwned, Deserialize};
+use prost_reflect::{DynamicMessage, MessageDescriptor, ReflectMessage};
+use serde::{Deserialize, Serialize};
 use tonic::body::BoxBody;
 
 use crate::{
-    method::GrpcMethod,
-    utils::{prost::MessageExt, tonic::CodeExt},
+    method::{GrpcMethod, MethodKind},
+    registry::{MethodTypes, TypeResolver},
+    utils::{
+        prost::{split_frames, MessageExt},
+        tonic::CodeExt,
+    },
     Error,
 };
 
@@ -29,25 +35,123 @@ impl MockSet {
     }
 
     /// Inserts [`Mock`]s from a mock file.
+    ///
+    /// All methods in the file must have the same input and output types.
     pub fn insert_from_file<I, O>(&mut self, path: impl AsRef<Path>) -> Result<(), Error>
     where
-        I: Message + DeserializeOwned,
-        O: Message + DeserializeOwned,
+        I: ReflectMessage + Default,
+        O: ReflectMessage + Default,
     {
-        let (method, mut mocks) = MockFile::read::<I, O>(path)?;
-        match self.0.entry(method) {
-            hash_map::Entry::Occupied(mut entry) => {
-                entry.get_mut().append(&mut mocks);
+        self.extend(MockFile::read_all::<I, O>(path)?);
+        Ok(())
+    }
+
+    /// Inserts [`Mock`]s from a mock file, using message types for each of the file's methods from `descriptors`,
+    /// e.g. a [`DescriptorPool`](prost_reflect::DescriptorPool) or [`TypeRegistry`](crate::registry::TypeRegistry).
+    pub fn insert_from_file_with_descriptors(
+        &mut self,
+        path: impl AsRef<Path>,
+        descriptors: &(impl TypeResolver + ?Sized),
+    ) -> Result<(), Error> {
+        self.extend(MockFile::read_all_with_descriptors(path, descriptors)?);
+        Ok(())
+    }
+
+    /// Inserts [`Mock`]s from all mock files in a directory and its subdirectories,
+    /// using message types for each file's method from `descriptors`.
+    ///
+    /// Only files with paths relative to `path` matching the glob `pattern` are read, e.g. `**/*.yaml`.
+    /// Files without an extension known to [`MockFormat::from_path`] are skipped, so `**/*` reads
+    /// mock files of every format.
+    pub fn load_dir(
+        &mut self,
+        path: impl AsRef<Path>,
+        pattern: &str,
+        descriptors: &(impl TypeResolver + ?Sized),
+    ) -> Result<(), Error> {
+        let root = path.as_ref();
+        let pattern = glob::Pattern::new(pattern)
+            .map_err(|error| Error::Invalid(format!("invalid pattern `{pattern}`: {error}")))?;
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        let mut dirs = vec![root.to_path_buf()];
+        // Canonical paths of visited directories, to not loop on symlink cycles
+        let mut visited = HashSet::new();
+        while let Some(dir) = dirs.pop() {
+            if !visited.insert(dir.canonicalize()?) {
+                continue;
             }
-            hash_map::Entry::Vacant(entry) => {
-                entry.insert(mocks);
+            let mut entries = std::fs::read_dir(dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            entries.sort();
+            for path in entries {
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if pattern.matches_path_with(path.strip_prefix(root).unwrap(), options)
+                    && MockFormat::from_path(&path).is_ok()
+                {
+                    self.insert_from_file_with_descriptors(&path, descriptors)
+                        .map_err(|error| {
+                            Error::Invalid(format!("mock file `{}`: {error}", path.display()))
+                        })?;
+                }
             }
         }
         Ok(())
     }
 
+    /// Converts to mock file sections, one per method sorted by service and method,
+    /// converting bodies to JSON using message types for each method from `descriptors`.
+    pub fn to_mock_files(
+        &self,
+        descriptors: &(impl TypeResolver + ?Sized),
+    ) -> Result<Vec<MockFile>, Error> {
+        let mut files = self
+            .iter()
+            .map(|(method, mocks)| MockFile::from_mocks(method, mocks, descriptors))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.sort_by(|a, b| (&a.service, &a.method).cmp(&(&b.service, &b.method)));
+        Ok(files)
+    }
+
+    /// Writes all mocks to a YAML mock file as a list of sections,
+    /// using message types for each method from `descriptors`.
+    pub fn write_file(
+        &self,
+        path: impl AsRef<Path>,
+        descriptors: &(impl TypeResolver + ?Sized),
+    ) -> Result<(), Error> {
+        let files = self.to_mock_files(descriptors)?;
+        std::fs::write(path, serde_yml::to_string(&files)?)?;
+        Ok(())
+    }
+
+    /// Writes mocks to YAML mock files in a directory, one file per method at `<service>/<method>.yaml`,
+    /// using message types for each method from `descriptors`.
+    ///
+    /// Existing files for the same methods are overwritten, the mocks can be read back with [`MockSet::load_dir`].
+    pub fn write_dir(
+        &self,
+        path: impl AsRef<Path>,
+        descriptors: &(impl TypeResolver + ?Sized),
+    ) -> Result<(), Error> {
+        for file in self.to_mock_files(descriptors)? {
+            file.write_to_dir(path.as_ref())?;
+        }
+        Ok(())
+    }
+
     /// Inserts a [`Mock`].
+    ///
+    /// If `method` has no [`MethodKind`], it is taken from the constructor used to create `mock`.
     pub fn insert(&mut self, method: GrpcMethod, mock: Mock) {
+        let method = match (method.kind(), mock.kind()) {
+            (None, Some(kind)) => method.with_kind(kind),
+            _ => method,
+        };
         match self.0.entry(method) {
             hash_map::Entry::Occupied(mut entry) => {
                 entry.get_mut().push(mock);
@@ -58,6 +162,23 @@ impl MockSet {
         }
     }
 
+    /// Removes all [`Mock`]s for a method, returning them if there were any.
+    pub fn remove(&mut self, method: &GrpcMethod) -> Option<Vec<Mock>> {
+        self.0.remove(method)
+    }
+
+    /// Removes all [`Mock`]s.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns an error if any mock doesn't match the [`MethodKind`] of its method.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.0
+            .iter()
+            .try_for_each(|(method, mocks)| mocks.iter().try_for_each(|mock| mock.validate(method)))
+    }
+
     /// Matches a [`Mock`] by method and request body.
     pub fn find(&self, method: &GrpcMethod, body: &[u8]) -> Option<&Mock> {
         self.0
@@ -72,6 +193,22 @@ impl FromIterator<(GrpcMethod, Vec<Mock>)> for MockSet {
     }
 }
 
+impl Extend<(GrpcMethod, Vec<Mock>)> for MockSet {
+    /// Appends [`Mock`]s to those already inserted for each method.
+    fn extend<T: IntoIterator<Item = (GrpcMethod, Vec<Mock>)>>(&mut self, iter: T) {
+        for (method, mut mocks) in iter {
+            match self.0.entry(method) {
+                hash_map::Entry::Occupied(mut entry) => {
+                    entry.get_mut().append(&mut mocks);
+                }
+                hash_map::Entry::Vacant(entry) => {
+                    entry.insert(mocks);
+                }
+            }
+        }
+    }
+}
+
 impl std::ops::Deref for MockSet {
     type Target = HashMap<GrpcMethod, Vec<Mock>>;
 
@@ -81,18 +218,33 @@ impl std::ops::Deref for MockSet {
 }
 
 /// A mock request and response pair.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Mock {
     pub request: MockRequest,
     pub response: MockResponse,
+    #[serde(skip)]
+    kind: Option<MethodKind>,
 }
 
 impl Mock {
+    /// Creates a [`Mock`] from a request and response, for a method of any [`MethodKind`].
+    pub fn new(request: MockRequest, response: MockResponse) -> Self {
+        Self {
+            request,
+            response,
+            kind: None,
+        }
+    }
+
     /// Creates a unary [`Mock`].
     pub fn unary(request: impl Message, response: impl Message) -> Self {
         let request = MockRequest::new(MockBody::Full(request.to_bytes()));
         let response = MockResponse::new(MockBody::Full(response.to_bytes()));
-        Self { request, response }
+        Self {
+            request,
+            response,
+            kind: Some(MethodKind::Unary),
+        }
     }
 
     /// Creates a client-streaming [`Mock`].
@@ -108,7 +260,11 @@ impl Mock {
             MockRequest::new(MockBody::Stream(body))
         };
         let response = MockResponse::new(MockBody::Full(response.to_bytes()));
-        Self { request, response }
+        Self {
+            request,
+            response,
+            kind: Some(MethodKind::ClientStreaming),
+        }
     }
 
     /// Creates a server-streaming [`Mock`].
@@ -124,7 +280,11 @@ impl Mock {
                 .collect::<Vec<_>>();
             MockResponse::new(MockBody::Stream(body))
         };
-        Self { request, response }
+        Self {
+            request,
+            response,
+            kind: Some(MethodKind::ServerStreaming),
+        }
     }
 
     /// Creates a bidi-streaming [`Mock`].
@@ -146,7 +306,16 @@ impl Mock {
                 .collect::<Vec<_>>();
             MockResponse::new(MockBody::Stream(body))
         };
-        Self { request, response }
+        Self {
+            request,
+            response,
+            kind: Some(MethodKind::BidiStreaming),
+        }
+    }
+
+    /// Returns the [`MethodKind`] of the constructor used to create the mock, if any.
+    pub fn kind(&self) -> Option<MethodKind> {
+        self.kind
     }
 
     pub fn with_code(mut self, code: http::StatusCode) -> Self {
@@ -164,27 +333,209 @@ impl Mock {
         self
     }
 
+    /// Sets the gRPC status code, taking precedence over the HTTP status code set with [`Mock::with_code`].
+    pub fn with_grpc_status(mut self, code: tonic::Code) -> Self {
+        self.response.grpc_status = Some(code);
+        self
+    }
+
+    /// Sets trailers, sent after the response body with the gRPC status.
+    pub fn with_trailers(mut self, trailers: HeaderMap) -> Self {
+        self.response.trailers = trailers;
+        self
+    }
+
+    /// Returns an error if the mock doesn't match the [`MethodKind`] of `method`.
+    fn validate(&self, method: &GrpcMethod) -> Result<(), Error> {
+        let Some(kind) = method.kind() else {
+            return Ok(());
+        };
+        if let Some(mock_kind) = self.kind {
+            if mock_kind != kind {
+                return Err(Error::Invalid(format!(
+                    "mock for {kind} method `{method}` is {mock_kind}"
+                )));
+            }
+            return Ok(());
+        }
+        let bodies = [
+            (
+                "request",
+                &self.request.json_body,
+                kind.is_client_streaming(),
+            ),
+            (
+                "response",
+                &self.response.json_body,
+                kind.is_server_streaming(),
+            ),
+        ];
+        for (name, body, streaming) in bodies {
+            let mismatch = match body {
+                JsonMockBody::Empty => false,
+                JsonMockBody::Full(_) => streaming,
+                JsonMockBody::Stream(_) => !streaming,
+            };
+            if mismatch {
+                let shape = if streaming { "single" } else { "stream" };
+                return Err(Error::Invalid(format!(
+                    "mock for {kind} method `{method}` has a {shape} {name} body"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode protobuf body ([`MockBody`]) to JSON body representation ([`JsonMockBody`]).
+    ///
+    /// Bodies are treated as streams if `kind` is streaming, or if they are [`MockBody::Stream`]s.
+    fn decode_body(&mut self, types: &MethodTypes, kind: Option<MethodKind>) -> Result<(), Error> {
+        let kind = kind.or(self.kind);
+        self.request.json_body = self.request.body.to_json(
+            &types.input,
+            kind.is_some_and(|kind| kind.is_client_streaming()),
+        )?;
+        self.response.json_body = self.response.body.to_json(
+            &types.output,
+            kind.is_some_and(|kind| kind.is_server_streaming()),
+        )?;
+
+        Ok(())
+    }
+
     /// Encode JSON body representation ([`JsonMockBody`]) to protobuf body ([`MockBody`]).
     fn encode_body<I, O>(&mut self) -> Result<(), Error>
     where
-        I: Message + DeserializeOwned,
-        O: Message + DeserializeOwned,
+        I: ReflectMessage + Default,
+        O: ReflectMessage + Default,
     {
         self.request.body = MockBody::from_json::<I>(&self.request.json_body, true)?;
         self.response.body = MockBody::from_json::<O>(&self.response.json_body, false)?;
 
         Ok(())
     }
+
+    /// Encode JSON body representation ([`JsonMockBody`]) to protobuf body ([`MockBody`]) using message descriptors.
+    fn encode_body_with_descriptors(
+        &mut self,
+        input: &MessageDescriptor,
+        output: &MessageDescriptor,
+    ) -> Result<(), Error> {
+        self.request.body =
+            MockBody::from_json_with_descriptor(&self.request.json_body, input, true)?;
+        self.response.body =
+            MockBody::from_json_with_descriptor(&self.response.json_body, output, false)?;
+
+        Ok(())
+    }
 }
 
 /// A mock body in JSON format.
-#[derive(Default, Debug, Clone, Deserialize)]
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum JsonMockBody {
     #[default]
     Empty,
-    Full(String),
-    Stream(Vec<String>),
+    Full(JsonMessage),
+    Stream(Vec<JsonMessage>),
+}
+
+/// A message in JSON format, either as a JSON string or as a structured value,
+/// e.g. a native YAML mapping.
+///
+/// Strings may instead hold the message in another format, marked by a prefix:
+/// - `textproto:` protobuf text format
+/// - `base64:` base64-encoded protobuf wire bytes
+/// - `file:` path of a file containing protobuf wire bytes, e.g. a `.binpb` file,
+///   relative to the mock file. Only supported in mock files read with [`MockFile::parse`],
+///   and paths may not be absolute or contain `..`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum JsonMessage {
+    Text(String),
+    Value(serde_json::Map<String, serde_json::Value>),
+    /// A `file:` body resolved against the directory of the mock file it was read from.
+    #[serde(skip)]
+    File(PathBuf),
+}
+
+impl JsonMessage {
+    /// Decodes the message using a message descriptor.
+    fn decode(&self, descriptor: &MessageDescriptor) -> Result<DynamicMessage, Error> {
+        let value = match self {
+            JsonMessage::Text(value) => value,
+            JsonMessage::Value(value) => {
+                return Ok(DynamicMessage::deserialize(descriptor.clone(), value)?)
+            }
+            JsonMessage::File(path) => {
+                let bytes = std::fs::read(path).map_err(|error| {
+                    Error::Invalid(format!("failed to read `{}`: {error}", path.display()))
+                })?;
+                return Ok(DynamicMessage::decode(
+                    descriptor.clone(),
+                    bytes.as_slice(),
+                )?);
+            }
+        };
+        if let Some(text) = value.strip_prefix("textproto:") {
+            Ok(DynamicMessage::parse_text_format(descriptor.clone(), text)?)
+        } else if let Some(encoded) = value.strip_prefix("base64:") {
+            let bytes = BASE64_STANDARD.decode(encoded.trim())?;
+            Ok(DynamicMessage::decode(
+                descriptor.clone(),
+                bytes.as_slice(),
+            )?)
+        } else if value.starts_with("file:") {
+            Err(Error::Invalid(
+                "`file:` bodies are only supported in mock files".into(),
+            ))
+        } else {
+            decode_json(descriptor, value)
+        }
+    }
+
+    /// Resolves a `file:` path against `dir`, rejecting paths that aren't relative to it.
+    fn resolve_file(&mut self, dir: &Path) -> Result<(), Error> {
+        if let JsonMessage::Text(value) = self {
+            if let Some(path) = value.strip_prefix("file:") {
+                let path = Path::new(path.trim());
+                if !path
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+                {
+                    return Err(Error::Invalid(format!(
+                        "`file:` body path `{}` must be relative to the mock file, without `..`",
+                        path.display()
+                    )));
+                }
+                *self = JsonMessage::File(dir.join(path));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl JsonMockBody {
+    /// Returns the body's messages.
+    fn messages_mut(&mut self) -> &mut [JsonMessage] {
+        match self {
+            JsonMockBody::Empty => &mut [],
+            JsonMockBody::Full(message) => std::slice::from_mut(message),
+            JsonMockBody::Stream(messages) => messages,
+        }
+    }
+}
+
+impl From<String> for JsonMessage {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&str> for JsonMessage {
+    fn from(value: &str) -> Self {
+        Self::Text(value.into())
+    }
 }
 
 /// A mock body in protobuf bytes format.
@@ -196,23 +547,117 @@ pub enum MockBody {
     Stream(Vec<Bytes>),
 }
 
+/// Decodes a JSON message using a message descriptor.
+fn decode_json(descriptor: &MessageDescriptor, value: &str) -> Result<DynamicMessage, Error> {
+    let mut deserializer = serde_json::Deserializer::from_str(value);
+    let message = DynamicMessage::deserialize(descriptor.clone(), &mut deserializer)?;
+    deserializer.end()?;
+    Ok(message)
+}
+
+/// Encodes a dynamic message to bytes for a HTTP body, with map entries sorted by key.
+///
+/// Map entries of a [`DynamicMessage`] are otherwise encoded in random order, which would not match
+/// the bytes sent by clients, e.g. for a `google.protobuf.Struct`.
+fn encode_dynamic(message: &DynamicMessage) -> Bytes {
+    fn encode_nested(number: u32, message: &DynamicMessage, buf: &mut Vec<u8>) {
+        let mut nested = Vec::new();
+        encode_fields(message, &mut nested);
+        prost::encoding::encode_key(number, prost::encoding::WireType::LengthDelimited, buf);
+        prost::encoding::encode_varint(nested.len() as u64, buf);
+        buf.extend(nested);
+    }
+
+    fn encode_fields(message: &DynamicMessage, buf: &mut Vec<u8>) {
+        for (field, value) in message.fields() {
+            match value {
+                prost_reflect::Value::Map(entries) => {
+                    let entry = field.kind().as_message().unwrap().clone();
+                    let (key_field, value_field) =
+                        (entry.map_entry_key_field(), entry.map_entry_value_field());
+                    let default = prost_reflect::Value::default_value_for_field(&value_field);
+                    let mut entries = entries.iter().collect::<Vec<_>>();
+                    entries.sort_by(|a, b| a.0.cmp(b.0));
+                    for (key, value) in entries {
+                        // Like prost, default keys and values are omitted
+                        let mut message = DynamicMessage::new(entry.clone());
+                        message.set_field(&key_field, key.clone().into());
+                        if *value != default {
+                            message.set_field(&value_field, value.clone());
+                        }
+                        encode_nested(field.number(), &message, buf);
+                    }
+                }
+                prost_reflect::Value::Message(nested) if !field.is_group() => {
+                    encode_nested(field.number(), nested, buf)
+                }
+                prost_reflect::Value::List(values)
+                    if field.kind().as_message().is_some() && !field.is_group() =>
+                {
+                    for value in values {
+                        encode_nested(field.number(), value.as_message().unwrap(), buf);
+                    }
+                }
+                value => {
+                    let mut message = DynamicMessage::new(message.descriptor());
+                    message.set_field(&field, value.clone());
+                    message.encode(buf).unwrap();
+                }
+            }
+        }
+        for field in message.unknown_fields() {
+            field.encode(buf);
+        }
+    }
+
+    let mut buf = vec![0; 5];
+    encode_fields(message, &mut buf);
+    let len = (buf.len() - 5) as u32;
+    buf[1..5].copy_from_slice(&len.to_be_bytes());
+    buf.into()
+}
+
 impl MockBody {
     /// Creates a [`MockBody`] from a [`JsonMockBody`].
+    ///
+    /// JSON is parsed using the canonical proto3 JSON mapping, fields omitted from the JSON are set to their defaults.
     pub fn from_json<T>(json_body: &JsonMockBody, flatten: bool) -> Result<Self, Error>
     where
-        T: Message + DeserializeOwned,
+        T: ReflectMessage + Default,
     {
+        let descriptor = T::default().descriptor();
+        Self::from_json_with(json_body, flatten, |value| {
+            // Transcode to `T` so fields are encoded in the same order as prost-generated clients
+            Ok(value.decode(&descriptor)?.transcode_to::<T>()?.to_bytes())
+        })
+    }
+
+    /// Creates a [`MockBody`] from a [`JsonMockBody`] using a message descriptor.
+    ///
+    /// JSON is parsed using the canonical proto3 JSON mapping, fields omitted from the JSON are set to their defaults.
+    pub fn from_json_with_descriptor(
+        json_body: &JsonMockBody,
+        descriptor: &MessageDescriptor,
+        flatten: bool,
+    ) -> Result<Self, Error> {
+        Self::from_json_with(json_body, flatten, |value| {
+            Ok(encode_dynamic(&value.decode(descriptor)?))
+        })
+    }
+
+    fn from_json_with(
+        json_body: &JsonMockBody,
+        flatten: bool,
+        encode: impl Fn(&JsonMessage) -> Result<Bytes, Error>,
+    ) -> Result<Self, Error> {
         use JsonMockBody::*;
         match json_body {
             Empty => Ok(MockBody::Empty),
-            Full(value) => {
-                let message = serde_json::from_str::<T>(value)?;
-                Ok(MockBody::Full(message.to_bytes()))
-            }
+            Full(value) => Ok(MockBody::Full(encode(value)?)),
             Stream(values) => {
                 let messages = values
                     .iter()
-                    .map(|value| Ok(serde_json::from_str::<T>(value)?.to_bytes()))
+                    .map(&encode)
                     .collect::<Result<Vec<_>, Error>>()?;
                 if flatten {
                     // Flatten to a single byte array
@@ -224,26 +669,73 @@ impl MockBody {
         }
     }
 
+    /// Converts to a [`JsonMockBody`] using a message descriptor,
+    /// the inverse of [`MockBody::from_json_with_descriptor`].
+    ///
+    /// A flattened stream, e.g. a client-streaming request body, is split into messages if `streaming` is `true`.
+    pub fn to_json(
+        &self,
+        descriptor: &MessageDescriptor,
+        streaming: bool,
+    ) -> Result<JsonMockBody, Error> {
+        let decode = |body: &[u8]| -> Result<Vec<JsonMessage>, Error> {
+            split_frames(body)?
+                .into_iter()
+                .map(|message| {
+                    let message = DynamicMessage::decode(descriptor.clone(), message)?;
+                    Ok(JsonMessage::Text(serde_json::to_string(&message)?))
+                })
+                .collect()
+        };
+        match self {
+            MockBody::Empty => Ok(JsonMockBody::Empty),
+            MockBody::Full(body) if streaming => Ok(JsonMockBody::Stream(decode(body)?)),
+            MockBody::Full(body) => {
+                let mut messages = decode(body)?;
+                if messages.len() != 1 {
+                    return Err(Error::Invalid(format!(
+                        "expected a single message, found {}",
+                        messages.len()
+                    )));
+                }
+                Ok(JsonMockBody::Full(messages.remove(0)))
+            }
+            MockBody::Stream(messages) => Ok(JsonMockBody::Stream(
+                messages
+                    .iter()
+                    .map(|message| decode(message))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+            )),
+        }
+    }
+
     /// Returns a type-erased HTTP body.
     pub fn to_boxed(&self) -> BoxBody {
         match self {
             MockBody::Empty => tonic::body::empty_body(),
             MockBody::Full(data) => tonic::body::boxed(Full::new(data.clone())),
             MockBody::Stream(data) => {
-                let messages: Vec<Result<_, tonic::Status>> = data
+                let messages: Vec<Result<_, Infallible>> = data
                     .iter()
                     .map(|message| Ok(Frame::data(message.clone())))
                     .collect();
-                BoxBody::new(StreamBody::new(futures::stream::iter(messages)))
+                tonic::body::boxed(StreamBody::new(futures::stream::iter(messages)))
             }
         }
     }
 }
 
 /// A mock request.
-#[derive(Default, Debug, Clone, Deserialize)]
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct MockRequest {
-    #[serde(default, with = "http_serde::header_map")]
+    #[serde(
+        default,
+        with = "http_serde::header_map",
+        skip_serializing_if = "HeaderMap::is_empty"
+    )]
     pub headers: HeaderMap,
     #[serde(rename = "body")]
     pub(crate) json_body: JsonMockBody,
@@ -269,17 +761,85 @@ impl MockRequest {
 }
 
 /// A mock response.
-#[derive(Default, Debug, Clone, Deserialize)]
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct MockResponse {
     #[serde(default, with = "http_serde::status_code")]
     pub code: http::StatusCode,
-    #[serde(default, with = "http_serde::header_map")]
+    /// gRPC status code, taking precedence over the HTTP status `code`.
+    #[serde(default, with = "grpc_status", skip_serializing_if = "Option::is_none")]
+    pub grpc_status: Option<tonic::Code>,
+    #[serde(
+        default,
+        with = "http_serde::header_map",
+        skip_serializing_if = "HeaderMap::is_empty"
+    )]
     pub headers: HeaderMap,
     #[serde(rename = "body", default)]
     pub(crate) json_body: JsonMockBody,
     #[serde(skip)]
     pub body: MockBody,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Trailers sent after the body, with the gRPC status.
+    #[serde(
+        default,
+        with = "http_serde::header_map",
+        skip_serializing_if = "HeaderMap::is_empty"
+    )]
+    pub trailers: HeaderMap,
+}
+
+/// Serializes a gRPC status code by its name, e.g. `INVALID_ARGUMENT`, deserializing a name or number.
+mod grpc_status {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+    use tonic::Code;
+
+    /// Names of gRPC status codes, indexed by code.
+    const NAMES: [&str; 17] = [
+        "OK",
+        "CANCELLED",
+        "UNKNOWN",
+        "INVALID_ARGUMENT",
+        "DEADLINE_EXCEEDED",
+        "NOT_FOUND",
+        "ALREADY_EXISTS",
+        "PERMISSION_DENIED",
+        "RESOURCE_EXHAUSTED",
+        "FAILED_PRECONDITION",
+        "ABORTED",
+        "OUT_OF_RANGE",
+        "UNIMPLEMENTED",
+        "INTERNAL",
+        "UNAVAILABLE",
+        "DATA_LOSS",
+        "UNAUTHENTICATED",
+    ];
+
+    pub fn serialize<S: Serializer>(code: &Option<Code>, serializer: S) -> Result<S::Ok, S::Error> {
+        match code {
+            Some(code) => serializer.serialize_str(NAMES[*code as usize]),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Code>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum NameOrNumber {
+            Name(String),
+            Number(i64),
+        }
+        let index = match NameOrNumber::deserialize(deserializer)? {
+            NameOrNumber::Name(name) => NAMES.iter().position(|known| *known == name),
+            NameOrNumber::Number(number) => usize::try_from(number).ok(),
+        };
+        match index {
+            Some(index) if index < NAMES.len() => Ok(Some(Code::from_i32(index as i32))),
+            _ => Err(D::Error::custom("invalid gRPC status code")),
+        }
+    }
 }
 
 impl MockResponse {
@@ -295,13 +855,18 @@ impl MockResponse {
     }
 
     pub fn grpc_code(&self) -> tonic::Code {
-        tonic::Code::from_http(self.code)
+        self.grpc_status
+            .unwrap_or_else(|| tonic::Code::from_http(self.code))
     }
 
     pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
     pub fn body(&self) -> &MockBody {
         &self.body
     }
@@ -333,8 +898,45 @@ impl From<Vec<Bytes>> for MockBody {
     }
 }
 
-/// A YAML file defining a set of mocks for a method.
-#[derive(Debug, Clone, Deserialize)]
+/// A mock file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl MockFormat {
+    /// Returns the format for a file's extension: `.yaml` or `.yml`, `.json`, or `.toml`.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            Some("json") => Ok(Self::Json),
+            Some("toml") => Ok(Self::Toml),
+            _ => Err(Error::Invalid(format!(
+                "unknown mock file format for `{}`",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Converts an error deserializing a mock file section to this format's error.
+    fn error(&self, error: serde_json::Error) -> Error {
+        use serde::de::Error as _;
+        match self {
+            Self::Yaml => serde_yml::Error::custom(error).into(),
+            Self::Json => error.into(),
+            Self::Toml => toml::de::Error::custom(error).into(),
+        }
+    }
+}
+
+/// A mock file section defining a set of mocks for a method.
+///
+/// A mock file may contain a single section, a list of sections, or a `methods` list of sections.
+/// YAML mock files may also contain a multi-document stream of any of these.
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct MockFile {
     pub service: String,
     pub method: String,
@@ -342,19 +944,205 @@ pub struct MockFile {
 }
 
 impl MockFile {
+    /// Parses all sections of a mock file, without converting bodies.
+    ///
+    /// The [`MockFormat`] is selected by the file's extension.
+    pub fn parse(path: impl AsRef<Path>) -> Result<Vec<MockFile>, Error> {
+        let path = path.as_ref();
+        Self::parse_with_format(path, MockFormat::from_path(path)?)
+    }
+
+    /// Parses all sections of a mock file in the given format, without converting bodies.
+    pub fn parse_with_format(
+        path: impl AsRef<Path>,
+        format: MockFormat,
+    ) -> Result<Vec<MockFile>, Error> {
+        let path = path.as_ref();
+        let mut sections = Self::parse_str(&std::fs::read_to_string(path)?, format)?;
+        if let Some(dir) = path.parent() {
+            for section in &mut sections {
+                section.resolve_files(dir)?;
+            }
+        }
+        Ok(sections)
+    }
+
+    /// Resolves relative `file:` body paths against `dir`.
+    fn resolve_files(&mut self, dir: &Path) -> Result<(), Error> {
+        for mock in &mut self.mocks {
+            let bodies = [&mut mock.request.json_body, &mut mock.response.json_body];
+            for message in bodies.into_iter().flat_map(JsonMockBody::messages_mut) {
+                message.resolve_file(dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses all sections of mock file contents in the given format, without converting bodies.
+    pub fn parse_str(contents: &str, format: MockFormat) -> Result<Vec<MockFile>, Error> {
+        let documents = match format {
+            MockFormat::Yaml => serde_yml::Deserializer::from_str(contents)
+                .map(serde_json::Value::deserialize)
+                .collect::<Result<Vec<_>, _>>()?,
+            MockFormat::Json => vec![serde_json::from_str(contents)?],
+            MockFormat::Toml => vec![toml::from_str(contents)?],
+        };
+        let mut sections = Vec::new();
+        for document in documents {
+            let values = match document {
+                serde_json::Value::Null => continue,
+                serde_json::Value::Array(values) => values,
+                serde_json::Value::Object(mut object)
+                    if !object.contains_key("service") && object.contains_key("methods") =>
+                {
+                    match object.remove("methods") {
+                        Some(serde_json::Value::Array(values)) => values,
+                        _ => return Err(Error::Invalid("`methods` should be a list".into())),
+                    }
+                }
+                value => vec![value],
+            };
+            for value in values {
+                sections.push(serde_json::from_value(value).map_err(|error| format.error(error))?);
+            }
+        }
+        Ok(sections)
+    }
+
+    /// Reads a mock file with a single section, converting bodies using prost-generated input and output types of the method.
+    ///
+    /// If the types' descriptor pool contains the method, mocks are validated against its [`MethodKind`].
     pub fn read<I, O>(path: impl AsRef<Path>) -> Result<(GrpcMethod, Vec<Mock>), Error>
     where
-        I: Message + DeserializeOwned,
-        O: Message + DeserializeOwned,
+        I: ReflectMessage + Default,
+        O: ReflectMessage + Default,
+    {
+        Self::parse_one(path)?.into_mocks::<I, O>()
+    }
+
+    /// Reads all sections of a mock file, converting bodies using prost-generated input and output types.
+    pub fn read_all<I, O>(path: impl AsRef<Path>) -> Result<Vec<(GrpcMethod, Vec<Mock>)>, Error>
+    where
+        I: ReflectMessage + Default,
+        O: ReflectMessage + Default,
+    {
+        Self::parse(path)?
+            .into_iter()
+            .map(|section| section.into_mocks::<I, O>())
+            .collect()
+    }
+
+    /// Reads a mock file with a single section, converting bodies using message types for the method from `descriptors`.
+    ///
+    /// If the method's [`MethodKind`] is known, mocks are validated against it.
+    pub fn read_with_descriptors(
+        path: impl AsRef<Path>,
+        descriptors: &(impl TypeResolver + ?Sized),
+    ) -> Result<(GrpcMethod, Vec<Mock>), Error> {
+        Self::parse_one(path)?.into_mocks_with_descriptors(descriptors)
+    }
+
+    /// Reads all sections of a mock file, converting bodies using message types for each method from `descriptors`.
+    pub fn read_all_with_descriptors(
+        path: impl AsRef<Path>,
+        descriptors: &(impl TypeResolver + ?Sized),
+    ) -> Result<Vec<(GrpcMethod, Vec<Mock>)>, Error> {
+        Self::parse(path)?
+            .into_iter()
+            .map(|section| section.into_mocks_with_descriptors(descriptors))
+            .collect()
+    }
+
+    /// Creates a mock file section from a method's [`Mock`]s,
+    /// converting bodies to JSON using message types for the method from `descriptors`.
+    pub fn from_mocks(
+        method: &GrpcMethod,
+        mocks: &[Mock],
+        descriptors: &(impl TypeResolver + ?Sized),
+    ) -> Result<MockFile, Error> {
+        let types = descriptors.resolve(method)?;
+        let kind = method.kind().or(types.kind);
+        let mocks = mocks
+            .iter()
+            .map(|mock| {
+                let mut mock = mock.clone();
+                mock.decode_body(&types, kind)?;
+                Ok(mock)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(MockFile {
+            service: method.service().to_string(),
+            method: method.name().to_string(),
+            mocks,
+        })
+    }
+
+    /// Writes the section to a YAML mock file.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        std::fs::write(path, serde_yml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Writes the section to a YAML mock file in a directory, at `<service>/<method>.yaml`.
+    pub(crate) fn write_to_dir(&self, path: &Path) -> Result<(), Error> {
+        let dir = path.join(&self.service);
+        std::fs::create_dir_all(&dir)?;
+        self.write(dir.join(format!("{}.yaml", self.method)))
+    }
+
+    fn parse_one(path: impl AsRef<Path>) -> Result<MockFile, Error> {
+        let mut sections = Self::parse(path)?;
+        if sections.len() != 1 {
+            return Err(Error::Invalid(format!(
+                "mock file should contain a single method, found {}",
+                sections.len()
+            )));
+        }
+        Ok(sections.remove(0))
+    }
+
+    /// Converts the section's bodies using prost-generated input and output types of the method.
+    pub fn into_mocks<I, O>(self) -> Result<(GrpcMethod, Vec<Mock>), Error>
+    where
+        I: ReflectMessage + Default,
+        O: ReflectMessage + Default,
     {
         let MockFile {
             service,
             method,
             mut mocks,
-        } = serde_yml::from_reader(File::open(path)?)?;
-        let method = GrpcMethod::new(service, method)?;
+        } = self;
+        let mut method = GrpcMethod::new(service, method)?;
+        if let Ok(descriptor) = method.descriptor(I::default().descriptor().parent_pool()) {
+            method = method.with_kind(MethodKind::from(&descriptor));
+        }
         for mock in mocks.iter_mut() {
+            mock.validate(&method)?;
             mock.encode_body::<I, O>()?;
+            mock.kind = method.kind();
+        }
+        Ok((method, mocks))
+    }
+
+    /// Converts the section's bodies using message types for the method from `descriptors`.
+    pub fn into_mocks_with_descriptors(
+        self,
+        descriptors: &(impl TypeResolver + ?Sized),
+    ) -> Result<(GrpcMethod, Vec<Mock>), Error> {
+        let MockFile {
+            service,
+            method,
+            mut mocks,
+        } = self;
+        let mut method = GrpcMethod::new(service, method)?;
+        let types = descriptors.resolve(&method)?;
+        if let Some(kind) = types.kind {
+            method = method.with_kind(kind);
+        }
+        for mock in mocks.iter_mut() {
+            mock.validate(&method)?;
+            mock.encode_body_with_descriptors(&types.input, &types.output)?;
+            mock.kind = method.kind();
         }
         Ok((method, mocks))
     }
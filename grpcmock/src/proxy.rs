@@ -0,0 +1,260 @@
+//! Forwarding requests to an upstream server, and recording its responses as mocks.
+use std::{
+    path::PathBuf,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use http::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE, DATE, TE},
+    HeaderMap, Request, Response,
+};
+use http_body::{Body, Frame};
+use tonic::{body::BoxBody, transport::Channel, Code};
+use tower::ServiceExt;
+
+use crate::{
+    method::GrpcMethod,
+    mock::{Mock, MockBody, MockFile, MockRequest, MockResponse, MockSet},
+    registry::TypeResolver,
+    utils::{prost::check_message_sizes, tonic::CodeExt},
+    Error,
+};
+
+/// Headers set by the transport or holding the gRPC status, which are not recorded.
+const TRANSPORT_HEADERS: [&str; 4] = [
+    "grpc-status",
+    "grpc-message",
+    "grpc-encoding",
+    "grpc-accept-encoding",
+];
+
+/// Forwards a request to `upstream`, streaming the request body and returning the streamed response.
+pub(crate) async fn forward(
+    upstream: Channel,
+    method: &GrpcMethod,
+    headers: HeaderMap,
+    body: BoxBody,
+) -> Result<Response<BoxBody>, tonic::Status> {
+    // The channel sets the URI's scheme and authority to the upstream's
+    let mut request = Request::post(method.path())
+        .body(body)
+        .map_err(|error| tonic::Status::internal(error.to_string()))?;
+    *request.headers_mut() = headers;
+    upstream
+        .oneshot(request)
+        .await
+        .map_err(|error| tonic::Status::unavailable(format!("upstream error: {error}")))
+}
+
+/// Called with a body's data and trailers once it has been read to the end.
+type OnEnd = Box<dyn FnOnce(Bytes, Option<HeaderMap>) + Send>;
+
+/// A body relaying the frames of another body as they are read, keeping a copy of its data.
+pub(crate) struct Tee {
+    body: BoxBody,
+    data: Arc<Mutex<BytesMut>>,
+    max_message_size: Option<usize>,
+    // Offset of the next message's length-prefix
+    next: usize,
+    on_end: Option<OnEnd>,
+}
+
+impl Tee {
+    pub fn new(body: BoxBody) -> Self {
+        Self {
+            body,
+            data: Arc::default(),
+            max_message_size: None,
+            next: 0,
+            on_end: None,
+        }
+    }
+
+    /// Fails the body with `RESOURCE_EXHAUSTED` if a message is larger than `max_message_size`.
+    pub fn max_message_size(mut self, max_message_size: Option<usize>) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Calls `on_end` with the body's data and trailers once it has been read to the end,
+    /// unless the body fails.
+    pub fn on_end(
+        mut self,
+        on_end: impl FnOnce(Bytes, Option<HeaderMap>) + Send + 'static,
+    ) -> Self {
+        self.on_end = Some(Box::new(on_end));
+        self
+    }
+
+    /// Returns the data read so far.
+    pub fn data(&self) -> Arc<Mutex<BytesMut>> {
+        self.data.clone()
+    }
+
+    fn end(&mut self, trailers: Option<HeaderMap>) {
+        if let Some(on_end) = self.on_end.take() {
+            let data = self.data.lock().unwrap().clone().freeze();
+            on_end(data, trailers);
+        }
+    }
+}
+
+impl Body for Tee {
+    type Data = Bytes;
+    type Error = tonic::Status;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+        let frame = match ready!(Pin::new(&mut this.body).poll_frame(cx)) {
+            Some(Ok(frame)) => frame,
+            Some(Err(status)) => {
+                this.on_end = None;
+                return Poll::Ready(Some(Err(status)));
+            }
+            None => {
+                this.end(None);
+                return Poll::Ready(None);
+            }
+        };
+        if let Some(data) = frame.data_ref() {
+            let mut copy = this.data.lock().unwrap();
+            copy.extend_from_slice(data);
+            if !check_message_sizes(&copy, &mut this.next, this.max_message_size) {
+                this.on_end = None;
+                return Poll::Ready(Some(Err(tonic::Status::resource_exhausted(
+                    "request exceeds maximum message size",
+                ))));
+            }
+        } else if let Some(trailers) = frame.trailers_ref() {
+            // Trailers end the body, which may not be polled again
+            this.end(Some(trailers.clone()));
+        }
+        Poll::Ready(Some(Ok(frame)))
+    }
+}
+
+/// A response from an upstream server, with its body collected.
+pub(crate) struct UpstreamResponse {
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    pub trailers: Option<HeaderMap>,
+}
+
+impl UpstreamResponse {
+    /// Returns the gRPC status, from trailers or, for trailers-only responses, headers.
+    pub fn status(&self) -> tonic::Status {
+        self.trailers
+            .as_ref()
+            .and_then(tonic::Status::from_header_map)
+            .or_else(|| tonic::Status::from_header_map(&self.headers))
+            .unwrap_or_else(|| tonic::Status::new(Code::Ok, ""))
+    }
+}
+
+/// Returns custom metadata, without headers set by the transport or holding the gRPC status.
+fn metadata(headers: &HeaderMap) -> HeaderMap {
+    let mut metadata = headers.clone();
+    for name in [CONTENT_TYPE, CONTENT_LENGTH, DATE, TE] {
+        metadata.remove(name);
+    }
+    for name in TRANSPORT_HEADERS {
+        metadata.remove(name);
+    }
+    metadata
+}
+
+/// Records calls forwarded to an upstream server as mocks, written to mock files.
+pub(crate) struct Recorder {
+    upstream: Channel,
+    dir: PathBuf,
+    descriptors: Arc<dyn TypeResolver + Send + Sync>,
+    mocks: Mutex<MockSet>,
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recorder")
+            .field("upstream", &self.upstream)
+            .field("dir", &self.dir)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Recorder {
+    pub fn new(
+        upstream: Channel,
+        dir: PathBuf,
+        descriptors: Arc<dyn TypeResolver + Send + Sync>,
+    ) -> Self {
+        Self {
+            upstream,
+            dir,
+            descriptors,
+            mocks: Mutex::default(),
+        }
+    }
+
+    /// Returns the upstream server channel.
+    pub fn upstream(&self) -> Channel {
+        self.upstream.clone()
+    }
+
+    /// Returns the recorded mocks.
+    pub fn mocks(&self) -> MockSet {
+        self.mocks.lock().unwrap().clone()
+    }
+
+    /// Records a request and upstream response as a mock, unless a mock for the request was already recorded,
+    /// and writes the method's recorded mocks to its mock file.
+    ///
+    /// The gRPC status, message, headers and trailers of the response are recorded as they were received.
+    pub fn record(
+        &self,
+        method: &GrpcMethod,
+        request: Bytes,
+        response: UpstreamResponse,
+    ) -> Result<(), Error> {
+        let types = self.descriptors.resolve(method)?;
+        let method = match types.kind {
+            Some(kind) => method.clone().with_kind(kind),
+            None => method.clone(),
+        };
+        // Held while writing, so concurrent calls write the method's file in turn
+        let mut mocks = self.mocks.lock().unwrap();
+        if mocks.find(&method, &request).is_some() {
+            return Ok(());
+        }
+        let body = |bytes: Bytes| {
+            if bytes.is_empty() {
+                MockBody::Empty
+            } else {
+                MockBody::Full(bytes)
+            }
+        };
+        let status = response.status();
+        let mut mock = Mock::new(
+            MockRequest::new(body(request)),
+            MockResponse::new(body(response.body)),
+        )
+        .with_code(status.code().to_http())
+        .with_headers(metadata(&response.headers));
+        if status.code() != Code::Ok {
+            mock = mock.with_grpc_status(status.code());
+        }
+        if !status.message().is_empty() {
+            mock = mock.with_error(status.message());
+        }
+        if let Some(trailers) = &response.trailers {
+            mock = mock.with_trailers(metadata(trailers));
+        }
+        mocks.insert(method.clone(), mock);
+        MockFile::from_mocks(&method, &mocks[&method], self.descriptors.as_ref())?
+            .write_to_dir(&self.dir)
+    }
+}
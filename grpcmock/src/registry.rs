@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+
+use prost_reflect::{DescriptorPool, MessageDescriptor, ReflectMessage};
+
+use crate::{
+    method::{GrpcMethod, MethodKind},
+    Error,
+};
+
+/// Input and output message types of a method.
+#[derive(Debug, Clone)]
+pub struct MethodTypes {
+    pub kind: Option<MethodKind>,
+    pub input: MessageDescriptor,
+    pub output: MessageDescriptor,
+}
+
+/// Resolves message types of methods, used to convert mock file bodies.
+///
+/// Implemented for [`DescriptorPool`] and [`TypeRegistry`].
+pub trait TypeResolver {
+    /// Returns the message types of `method`.
+    fn resolve(&self, method: &GrpcMethod) -> Result<MethodTypes, Error>;
+}
+
+impl TypeResolver for DescriptorPool {
+    fn resolve(&self, method: &GrpcMethod) -> Result<MethodTypes, Error> {
+        let descriptor = method.descriptor(self)?;
+        Ok(MethodTypes {
+            kind: Some(MethodKind::from(&descriptor)),
+            input: descriptor.input(),
+            output: descriptor.output(),
+        })
+    }
+}
+
+/// A registry of prost-generated message types, keyed by method.
+#[derive(Default, Debug, Clone)]
+pub struct TypeRegistry(HashMap<GrpcMethod, MethodTypes>);
+
+impl TypeRegistry {
+    /// Creates an empty [`TypeRegistry`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers prost-generated input and output types of a method.
+    ///
+    /// If the types' descriptor pool contains the method, its [`MethodKind`] is registered too.
+    pub fn register<I, O>(&mut self, method: GrpcMethod)
+    where
+        I: ReflectMessage + Default,
+        O: ReflectMessage + Default,
+    {
+        let input = I::default().descriptor();
+        let kind = method.kind().or_else(|| {
+            let descriptor = method.descriptor(input.parent_pool()).ok()?;
+            Some(MethodKind::from(&descriptor))
+        });
+        let types = MethodTypes {
+            kind,
+            input,
+            output: O::default().descriptor(),
+        };
+        self.0.insert(method, types);
+    }
+}
+
+impl TypeResolver for TypeRegistry {
+    fn resolve(&self, method: &GrpcMethod) -> Result<MethodTypes, Error> {
+        self.0
+            .get(method)
+            .cloned()
+            .ok_or_else(|| Error::Invalid(format!("method `{method}` not registered")))
+    }
+}
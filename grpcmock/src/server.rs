@@ -1,86 +1,480 @@
-use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+use std::{
+    convert::Infallible,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    task::{Context, Poll},
+    time::Duration,
+};
 
-use http::{Request, Response};
-use http_body_util::BodyExt;
-use tokio::net::TcpStream;
+use bytes::{Buf, Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use http::{header::CONTENT_TYPE, HeaderValue, Request, Response};
+use http_body::Frame;
+use http_body_util::{BodyExt, Full, StreamBody};
+use hyper_util::rt::TokioIo;
+use tempfile::TempDir;
+use tokio::{
+    io::DuplexStream,
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tokio_stream::wrappers::{TcpListenerStream, UnboundedReceiverStream};
 use tonic::{
     body::BoxBody,
-    codegen::{http, Body, BoxFuture, StdError},
+    codegen::{http, Body, BoxFuture, Service, StdError},
+    service::Routes,
+    transport::{Channel, Endpoint, Uri},
     Code,
 };
-use tracing::debug;
+pub use tonic_health::{server::HealthReporter, ServingStatus};
+use tower::{service_fn, util::BoxCloneService, ServiceExt};
+use tracing::{debug, info, warn};
+
+use crate::{
+    journal::{Journal, JournalEntry},
+    method::GrpcMethod,
+    mock::{MockBody, MockResponse, MockSet},
+    proxy::{self, Recorder, Tee, UpstreamResponse},
+    registry::TypeResolver,
+    utils::prost::check_message_sizes,
+    Error,
+};
 
-use crate::{method::GrpcMethod, mock::MockSet, utils::find_available_port, Error};
+const DUPLEX_BUFFER_SIZE: usize = 64 * 1024;
 
-const CONNECT_TIMEOUT_DURATION: Duration = Duration::from_millis(30);
-const CONNECT_RETRY_SLEEP_DURATION: Duration = Duration::from_millis(30);
-const CONNECT_RETRY_MAX_ATTEMPTS: i32 = 10;
+/// A server reflection service, boxed so it can be rebuilt when hosted services change.
+type ReflectionService = BoxCloneService<Request<axum::body::Body>, Response<BoxBody>, Infallible>;
+
+/// The `grpc.reflection.v1` and `v1alpha` server reflection services.
+#[derive(Debug, Clone)]
+struct Reflection {
+    v1: ReflectionService,
+    v1alpha: ReflectionService,
+}
 
 /// State for a [`MockServer`].
 #[derive(Debug)]
 struct MockServerState {
-    pub mocks: MockSet,
+    pub services: Vec<String>,
+    pub initial_mocks: RwLock<MockSet>,
+    pub mocks: RwLock<MockSet>,
+    pub journal: Journal,
+    /// Health reporter, set when the server is started with the health service.
+    pub health: OnceLock<HealthReporter>,
+    /// Sends advertised services to the task updating their health statuses.
+    pub health_updates: OnceLock<mpsc::UnboundedSender<Vec<String>>>,
+    /// Reflection services, set when the server is started with reflection.
+    pub reflection: Mutex<Option<Reflection>>,
+    /// Hosted services advertised by the health and reflection services.
+    pub advertised: Mutex<Vec<String>>,
 }
 
 impl MockServerState {
-    pub fn new(mocks: MockSet) -> Self {
-        Self { mocks }
+    pub fn new(services: Vec<String>, mocks: MockSet) -> Self {
+        Self {
+            services,
+            initial_mocks: RwLock::new(mocks.clone()),
+            mocks: RwLock::new(mocks),
+            journal: Journal::default(),
+            health: OnceLock::new(),
+            health_updates: OnceLock::new(),
+            reflection: Mutex::default(),
+            advertised: Mutex::default(),
+        }
+    }
+
+    /// Returns `true` if the method's service is hosted, i.e. one of the server's services,
+    /// or a service with mocks if the server hosts all services in its [`MockSet`].
+    pub fn hosts_service(&self, method: &GrpcMethod) -> bool {
+        if self.services.is_empty() {
+            let mocks = self.mocks.read().unwrap();
+            mocks.keys().any(|key| key.service() == method.service())
+        } else {
+            self.services.iter().any(|name| name == method.service())
+        }
+    }
+
+    /// Returns `true` if the method's service is hosted and the method has mocks.
+    pub fn is_hosted(&self, method: &GrpcMethod) -> bool {
+        self.hosts_service(method) && self.mocks.read().unwrap().contains_key(method)
     }
 }
 
 /// A mock gRPC server.
 #[derive(Clone)]
 pub struct MockServer {
-    name: &'static str,
     addr: SocketAddr,
+    uds_path: Option<PathBuf>,
+    config: MockServerConfig,
     state: Arc<MockServerState>,
     inner: Arc<Option<Inner>>,
 }
 
 impl MockServer {
-    /// Creates a new [`MockServer`].
-    pub fn new(name: &'static str, mocks: MockSet) -> Result<Self, Error> {
-        if mocks.iter().any(|(method, _)| method.service() != name) {
-            return Err(Error::Invalid(format!(
-                "all mocks must be for `{name}` service"
-            )));
-        }
-        let port = find_available_port().unwrap();
-        let addr: SocketAddr = format!("0.0.0.0:{}", port).parse().unwrap();
-        Ok(Self {
-            name,
-            addr,
-            state: Arc::new(MockServerState::new(mocks)),
-            inner: Arc::default(),
-        })
+    /// Creates a new [`MockServer`] for a service with default configuration.
+    pub fn new(name: impl Into<String>, mocks: MockSet) -> Result<Self, Error> {
+        Self::builder().service(name).build(mocks)
     }
 
-    /// Returns the server's service name.
+    /// Creates a [`MockServerBuilder`] to configure a [`MockServer`].
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::default()
+    }
+
+    /// Starts a [`MockServer`] with default configuration, hosting all services in `mocks`.
+    ///
+    /// Requests for services or methods without mocks return `UNIMPLEMENTED`.
+    pub async fn start(mocks: MockSet) -> Result<Self, Error> {
+        Self::builder().start(mocks).await
+    }
+
+    /// Returns the server's first service name.
+    #[deprecated(note = "use `MockServer::services` instead")]
     pub fn name(&self) -> &str {
-        self.name
+        self.state.services.first().map_or("", String::as_str)
+    }
+
+    /// Returns the names of services hosted by the server.
+    ///
+    /// Empty if the server hosts all services in its [`MockSet`].
+    pub fn services(&self) -> &[String] {
+        &self.state.services
+    }
+
+    /// Returns the server's [`HealthReporter`], if the health service is enabled and the server is started.
+    ///
+    /// Use [`HealthReporter::set_service_status`] to change the status reported for a service,
+    /// which is streamed to clients watching it.
+    pub fn health(&self) -> Option<HealthReporter> {
+        self.state.health.get().cloned()
+    }
+
+    /// Returns the names of services hosted by the server, or services in its [`MockSet`] if none were added.
+    fn hosted_services(&self) -> Vec<String> {
+        if self.state.services.is_empty() {
+            let mut services = self
+                .state
+                .mocks
+                .read()
+                .unwrap()
+                .keys()
+                .map(|method| method.service().to_string())
+                .collect::<Vec<_>>();
+            services.sort();
+            services.dedup();
+            services
+        } else {
+            self.state.services.clone()
+        }
+    }
+
+    /// Updates the services advertised by the health and reflection services after mocks change,
+    /// if the server hosts all services in its [`MockSet`].
+    ///
+    /// Added services report `SERVING`, and statuses of removed services are cleared. Health statuses
+    /// are updated by a task on the server's runtime, so they change shortly after this returns.
+    fn refresh_services(&self) {
+        let mut advertised = self.state.advertised.lock().unwrap();
+        let services = self.hosted_services();
+        if *advertised == services {
+            return;
+        }
+        if let Some(updates) = self.state.health_updates.get() {
+            let _ = updates.send(services.clone());
+        }
+        let mut reflection = self.state.reflection.lock().unwrap();
+        if reflection.is_some() {
+            match build_reflection(&self.config, &services) {
+                Ok(services) => *reflection = Some(services),
+                Err(error) => warn!(%error, "failed to update reflection services"),
+            }
+        }
+        *advertised = services;
+    }
+
+    /// Returns a snapshot of the server's mocks.
+    pub fn mocks(&self) -> MockSet {
+        self.state.mocks.read().unwrap().clone()
+    }
+
+    /// Replaces the server's mocks, which takes effect for subsequent requests.
+    ///
+    /// `mocks` also become the mocks restored by [`MockServer::reset_mocks`].
+    ///
+    /// Returns an error and keeps the current mocks if `mocks` are invalid for the server's services.
+    pub fn set_mocks(&self, mocks: MockSet) -> Result<(), Error> {
+        {
+            let mut current = self.state.mocks.write().unwrap();
+            validate_mocks(&self.state.services, &mocks)?;
+            *self.state.initial_mocks.write().unwrap() = mocks.clone();
+            *current = mocks;
+        }
+        self.refresh_services();
+        Ok(())
+    }
+
+    /// Updates the server's mocks atomically with respect to requests and other updates.
+    ///
+    /// Returns an error and keeps the current mocks if the updated mocks are invalid for the server's services.
+    pub fn update_mocks(&self, update: impl FnOnce(&mut MockSet)) -> Result<(), Error> {
+        {
+            let mut mocks = self.state.mocks.write().unwrap();
+            let mut updated = mocks.clone();
+            update(&mut updated);
+            validate_mocks(&self.state.services, &updated)?;
+            *mocks = updated;
+        }
+        self.refresh_services();
+        Ok(())
+    }
+
+    /// Returns the mocks recorded by the server, if it was built with [`MockServerBuilder::record`].
+    pub fn recorded_mocks(&self) -> Option<MockSet> {
+        self.config
+            .recorder
+            .as_ref()
+            .map(|recorder| recorder.mocks())
+    }
+
+    /// Resets the server's mocks to those it was built with, or last set with [`MockServer::set_mocks`].
+    ///
+    /// Changes made with [`MockServer::update_mocks`] are discarded.
+    pub fn reset_mocks(&self) {
+        {
+            let mut mocks = self.state.mocks.write().unwrap();
+            *mocks = self.state.initial_mocks.read().unwrap().clone();
+        }
+        self.refresh_services();
     }
 
     /// Returns the server's address.
+    ///
+    /// The port is assigned by the OS when the server is started.
     pub fn addr(&self) -> SocketAddr {
         self.addr
     }
 
+    /// Returns the server's Unix domain socket path, if listening on a Unix domain socket.
+    pub fn uds_path(&self) -> Option<&Path> {
+        self.uds_path.as_deref()
+    }
+
+    /// Returns the requests received by the server.
+    pub fn journal(&self) -> Vec<JournalEntry> {
+        self.state.journal.entries()
+    }
+
+    /// Clears the requests received by the server.
+    pub fn reset_journal(&self) {
+        self.state.journal.clear();
+    }
+
     #[doc(hidden)]
-    pub async fn _start(
-        &mut self,
-        handle: tokio::task::JoinHandle<Result<(), tonic::transport::Error>>,
-    ) {
-        for _ in 0..CONNECT_RETRY_MAX_ATTEMPTS {
-            if tokio::time::timeout(CONNECT_TIMEOUT_DURATION, TcpStream::connect(self.addr()))
-                .await
-                .is_ok()
-            {
-                debug!("{} server listening on {}", self.name(), self.addr());
-                break;
+    pub async fn _serve<S>(&mut self, service: S) -> Result<(), Error>
+    where
+        S: Service<Request<axum::body::Body>, Response = Response<BoxBody>, Error = Infallible>
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        let config = &self.config;
+        #[allow(unused_mut)]
+        let mut server = tonic::transport::Server::builder()
+            .http2_keepalive_interval(config.http2_keepalive_interval)
+            .http2_keepalive_timeout(config.http2_keepalive_timeout)
+            .max_concurrent_streams(config.max_concurrent_streams)
+            .max_frame_size(config.max_frame_size);
+        #[cfg(feature = "tls")]
+        if let Some(identity) = &config.tls_identity {
+            let mut tls = tonic::transport::ServerTlsConfig::new().identity(identity.clone());
+            if let Some(client_ca) = &config.tls_client_ca {
+                tls = tls.client_ca_root(client_ca.clone());
+            }
+            server = server.tls_config(tls)?;
+        }
+        let hosted_services = self.hosted_services();
+        let mut router = axum::Router::new();
+        if !config.file_descriptor_sets.is_empty() {
+            // Routed through the state, as the services are rebuilt when hosted services change
+            *self.state.reflection.lock().unwrap() =
+                Some(build_reflection(config, &hosted_services)?);
+            for (name, v1) in [
+                ("grpc.reflection.v1.ServerReflection", true),
+                ("grpc.reflection.v1alpha.ServerReflection", false),
+            ] {
+                let state = self.state.clone();
+                let reflection = service_fn(move |req| {
+                    let reflection = state.reflection.lock().unwrap().clone().unwrap(); // set above
+                    let service = if v1 {
+                        reflection.v1
+                    } else {
+                        reflection.v1alpha
+                    };
+                    service.oneshot(req)
+                });
+                router = router.route_service(&format!("/{name}/*method"), reflection);
+            }
+        }
+        // Requests for all other services are routed to the mock service, which matches by path
+        let mut routes = Routes::from(router.fallback_service(service));
+        if config.health {
+            let (mut reporter, health_service) = tonic_health::server::health_reporter();
+            for service in &hosted_services {
+                reporter
+                    .set_service_status(service, ServingStatus::Serving)
+                    .await;
+            }
+            routes = routes.add_service(health_service);
+            let (updates_tx, updates_rx) = mpsc::unbounded_channel();
+            tokio::spawn(update_health(
+                reporter.clone(),
+                hosted_services.clone(),
+                updates_rx,
+            ));
+            let _ = self.state.health.set(reporter);
+            let _ = self.state.health_updates.set(updates_tx);
+        }
+        *self.state.advertised.lock().unwrap() = hosted_services;
+        let router = server.add_routes(routes);
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let mut uds_dir = None;
+        let mut connections = None;
+        let handle = match config.transport {
+            Transport::Tcp => {
+                let listener = TcpListener::bind(self.addr).await?;
+                self.addr = listener.local_addr()?;
+                let nodelay = config.tcp_nodelay;
+                let incoming = TcpListenerStream::new(listener).inspect(move |conn| {
+                    if let Ok(stream) = conn {
+                        if let Err(error) = stream.set_nodelay(nodelay) {
+                            warn!(%error, "failed to set TCP_NODELAY");
+                        }
+                    }
+                });
+                let incoming = Incoming::new(incoming, ready_tx);
+                tokio::spawn(router.serve_with_incoming(incoming))
+            }
+            #[cfg(unix)]
+            Transport::Uds => {
+                let dir = tempfile::Builder::new().prefix("grpcmock").tempdir()?;
+                let uds_path = dir.path().join("grpc.sock");
+                let listener = tokio::net::UnixListener::bind(&uds_path)?;
+                let incoming = Incoming::new(
+                    tokio_stream::wrappers::UnixListenerStream::new(listener),
+                    ready_tx,
+                );
+                self.uds_path = Some(uds_path);
+                uds_dir = Some(dir);
+                tokio::spawn(router.serve_with_incoming(incoming))
+            }
+            Transport::InMemory => {
+                let (tx, rx) = mpsc::unbounded_channel::<DuplexStream>();
+                let incoming = Incoming::new(
+                    UnboundedReceiverStream::new(rx).map(Ok::<_, std::io::Error>),
+                    ready_tx,
+                );
+                connections = Some(tx);
+                tokio::spawn(router.serve_with_incoming(incoming))
             }
-            tokio::time::sleep(CONNECT_RETRY_SLEEP_DURATION).await;
+        };
+        let handle = wait_until_ready(handle, ready_rx).await?;
+        let services = self.services().join(", ");
+        match config.transport {
+            Transport::Tcp => debug!("{services} server listening on {}", self.addr()),
+            #[cfg(unix)]
+            Transport::Uds => debug!(
+                "{services} server listening on {}",
+                self.uds_path.as_ref().unwrap().display() // set above
+            ),
+            Transport::InMemory => debug!("{services} server listening in-memory"),
         }
-        self.inner = Arc::new(Some(Inner { handle }));
+        self.inner = Arc::new(Some(Inner {
+            handle,
+            uds_dir,
+            connections,
+        }));
+        Ok(())
+    }
+
+    /// Connects to the server, returning a [`Channel`] for a client.
+    pub async fn channel(&self) -> Result<Channel, Error> {
+        self.connect(Endpoint::from_static("http://[::]:50051"))
+            .await
+    }
+
+    /// Connects to a TLS server, returning a [`Channel`] for a client.
+    ///
+    /// The client configuration should trust the server's CA and, for mutual TLS, present a
+    /// client identity. Set [`ClientTlsConfig::domain_name`] to a name in the server's certificate.
+    ///
+    /// [`ClientTlsConfig::domain_name`]: tonic::transport::ClientTlsConfig::domain_name
+    #[cfg(feature = "tls")]
+    pub async fn channel_with_tls(
+        &self,
+        tls: tonic::transport::ClientTlsConfig,
+    ) -> Result<Channel, Error> {
+        self.connect(Endpoint::from_static("https://[::]:50051").tls_config(tls)?)
+            .await
+    }
+
+    async fn connect(&self, endpoint: Endpoint) -> Result<Channel, Error> {
+        let Some(inner) = self.inner.as_ref() else {
+            return Err(Error::Invalid("server is not started".into()));
+        };
+        // The URI is ignored by connectors
+        let channel = match self.config.transport {
+            Transport::Tcp => {
+                let mut addr = self.addr;
+                if addr.ip().is_unspecified() {
+                    match addr {
+                        SocketAddr::V4(_) => addr.set_ip(Ipv4Addr::LOCALHOST.into()),
+                        SocketAddr::V6(_) => addr.set_ip(Ipv6Addr::LOCALHOST.into()),
+                    }
+                }
+                endpoint
+                    .connect_with_connector(service_fn(move |_: Uri| async move {
+                        let stream = TcpStream::connect(addr).await?;
+                        Ok::<_, std::io::Error>(TokioIo::new(stream))
+                    }))
+                    .await?
+            }
+            #[cfg(unix)]
+            Transport::Uds => {
+                let uds_path = self.uds_path.clone().unwrap(); // set when started
+                endpoint
+                    .connect_with_connector(service_fn(move |_: Uri| {
+                        let uds_path = uds_path.clone();
+                        async move {
+                            let stream = tokio::net::UnixStream::connect(uds_path).await?;
+                            Ok::<_, std::io::Error>(TokioIo::new(stream))
+                        }
+                    }))
+                    .await?
+            }
+            Transport::InMemory => {
+                let connections = inner.connections.clone().unwrap(); // set when started
+                endpoint
+                    .connect_with_connector(service_fn(move |_: Uri| {
+                        let (client, server) = tokio::io::duplex(DUPLEX_BUFFER_SIZE);
+                        let result = connections
+                            .send(server)
+                            .map(|_| TokioIo::new(client))
+                            .map_err(|_| {
+                                std::io::Error::new(
+                                    std::io::ErrorKind::ConnectionRefused,
+                                    "server stopped",
+                                )
+                            });
+                        async move { result }
+                    }))
+                    .await?
+            }
+        };
+        Ok(channel)
     }
 }
 
@@ -93,22 +487,109 @@ impl MockServer {
         B::Error: Into<StdError> + Send + std::fmt::Debug + 'static,
     {
         let state = self.state.clone();
+        let max_message_size = self.config.max_message_size;
+        let recorder = self.config.recorder.clone();
+        // Requests are forwarded to the recording upstream, or unmatched requests to the passthrough upstream
+        let upstream = match &recorder {
+            Some(recorder) => Some(recorder.upstream()),
+            None => self.config.passthrough.clone(),
+        };
         let fut = async move {
-            let method: GrpcMethod = req.uri().path().parse().unwrap();
+            let method: GrpcMethod = match req.uri().path().parse() {
+                Ok(method) => method,
+                Err(error) => {
+                    debug!(path = req.uri().path(), %error, "invalid method path");
+                    return Ok(grpc_response(
+                        Code::Unimplemented,
+                        tonic::body::empty_body(),
+                        Some(&format!("method `{}` is not mocked", req.uri().path())),
+                    ));
+                }
+            };
             debug!(%method, "handling request");
+            // Only requests for hosted services are forwarded, or for any service when recording
+            // without added services
+            let hosted = match (&recorder, &upstream) {
+                (Some(_), _) if state.services.is_empty() => true,
+                (_, Some(_)) => state.hosts_service(&method),
+                (_, None) => state.is_hosted(&method),
+            };
+            if !hosted {
+                warn!(%method, "method is not mocked");
+                return Ok(grpc_response(
+                    Code::Unimplemented,
+                    tonic::body::empty_body(),
+                    Some(&format!("method `{method}` is not mocked")),
+                ));
+            }
+            let (parts, body) = req.into_parts();
+
+            // Stream requests that can't match a mock to the upstream
+            let stream_upstream = match &recorder {
+                Some(_) => upstream.clone(),
+                None => upstream
+                    .clone()
+                    .filter(|_| !state.mocks.read().unwrap().contains_key(&method)),
+            };
+            if let Some(upstream) = stream_upstream {
+                return Ok(stream_to_upstream(
+                    state,
+                    recorder,
+                    upstream,
+                    method,
+                    parts,
+                    body,
+                    max_message_size,
+                )
+                .await);
+            }
 
             // Collect request body
-            let body = req.into_body().collect().await.unwrap().to_bytes();
+            let body = match collect_body(body, max_message_size).await {
+                Ok(body) => body,
+                Err(status) => {
+                    debug!(%method, %status, "failed to read request body");
+                    return Ok(grpc_response(
+                        status.code(),
+                        tonic::body::empty_body(),
+                        Some(status.message()),
+                    ));
+                }
+            };
 
             // Match to mock and send response
-            if let Some(mock) = state.mocks.find(&method, &body) {
-                Ok(grpc_response(
-                    mock.response.grpc_code(),
-                    mock.response.body().to_boxed(),
-                    mock.response.error(),
-                ))
+            let mock = state.mocks.read().unwrap().find(&method, &body).cloned();
+            state.journal.push(JournalEntry {
+                method: method.clone(),
+                headers: parts.headers.clone(),
+                body: body.clone(),
+                matched: mock.is_some(),
+                proxied: mock.is_none() && upstream.is_some(),
+                client_certs: client_certs(&parts.extensions),
+            });
+            if let Some(mock) = mock {
+                info!(%method, "request matched mock");
+                Ok(mock_response(&mock.response))
+            } else if let Some(upstream) = upstream {
+                // Forward unmatched requests to the passthrough upstream
+                let body = tonic::body::boxed(Full::new(body));
+                match proxy::forward(upstream, &method, parts.headers, body).await {
+                    Ok(response) => {
+                        info!(%method, "request forwarded to upstream");
+                        Ok(response)
+                    }
+                    Err(status) => {
+                        warn!(%method, %status, "failed to forward request");
+                        Ok(grpc_response(
+                            status.code(),
+                            tonic::body::empty_body(),
+                            Some(status.message()),
+                        ))
+                    }
+                }
             } else {
                 // Request not matched to mock, send error response
+                warn!(%method, "request did not match any mock");
                 Ok(grpc_response(
                     Code::NotFound,
                     tonic::body::empty_body(),
@@ -120,20 +601,642 @@ impl MockServer {
     }
 }
 
+impl<B> Service<Request<B>> for MockServer
+where
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<StdError> + Send + std::fmt::Debug + 'static,
+{
+    type Response = Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        self.handle(req)
+    }
+}
+
+/// A builder for a [`MockServer`].
+#[derive(Default, Debug, Clone)]
+pub struct MockServerBuilder {
+    services: Vec<String>,
+    config: MockServerConfig,
+}
+
+impl MockServerBuilder {
+    /// Sets the address to bind to.
+    ///
+    /// Defaults to `0.0.0.0:0`. A port of `0` is assigned by the OS when the server is started.
+    pub fn addr(mut self, addr: impl Into<SocketAddr>) -> Self {
+        self.config.addr = addr.into();
+        self
+    }
+
+    /// Sets the IP address to bind to, e.g. [`Ipv4Addr::LOCALHOST`] for loopback-only or
+    /// [`Ipv6Addr::UNSPECIFIED`] for IPv6.
+    ///
+    /// [`Ipv4Addr::LOCALHOST`]: std::net::Ipv4Addr::LOCALHOST
+    /// [`Ipv6Addr::UNSPECIFIED`]: std::net::Ipv6Addr::UNSPECIFIED
+    pub fn ip(mut self, ip: impl Into<IpAddr>) -> Self {
+        self.config.addr.set_ip(ip.into());
+        self
+    }
+
+    /// Sets the port to bind to.
+    pub fn port(mut self, port: u16) -> Self {
+        self.config.addr.set_port(port);
+        self
+    }
+
+    /// Listens on a Unix domain socket instead of TCP.
+    ///
+    /// The socket is created at a temporary path managed by the server, see [`MockServer::uds_path`].
+    #[cfg(unix)]
+    pub fn uds(mut self) -> Self {
+        self.config.transport = Transport::Uds;
+        self
+    }
+
+    /// Serves in-memory instead of over a network socket.
+    ///
+    /// Clients connect with [`MockServer::channel`].
+    pub fn in_memory(mut self) -> Self {
+        self.config.transport = Transport::InMemory;
+        self
+    }
+
+    /// Serves the `grpc.health.v1.Health` service, reporting `SERVING` for hosted services.
+    ///
+    /// Statuses can be changed at runtime with [`MockServer::health`]. If the server hosts all
+    /// services in its [`MockSet`], services added with mocks at runtime report `SERVING` too. The
+    /// built-in service takes precedence over mocks for `grpc.health.v1.Health`.
+    pub fn health(mut self) -> Self {
+        self.config.health = true;
+        self
+    }
+
+    /// Registers an encoded `FileDescriptorSet` and serves the `grpc.reflection.v1` (and `v1alpha`)
+    /// server reflection service, advertising hosted services, including those added with mocks at runtime.
+    ///
+    /// May be called multiple times to register several sets.
+    pub fn reflection(mut self, file_descriptor_set: impl Into<Bytes>) -> Self {
+        self.config
+            .file_descriptor_sets
+            .push(file_descriptor_set.into());
+        self
+    }
+
+    /// Serves over TLS with a PEM-encoded certificate chain and private key.
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, cert: impl AsRef<[u8]>, key: impl AsRef<[u8]>) -> Self {
+        self.config.tls_identity = Some(tonic::transport::Identity::from_pem(cert, key));
+        self
+    }
+
+    /// Requires clients to present a certificate signed by a PEM-encoded CA certificate (mutual TLS).
+    ///
+    /// Has no effect unless TLS is enabled with [`MockServerBuilder::tls`].
+    #[cfg(feature = "tls")]
+    pub fn tls_client_ca(mut self, ca: impl AsRef<[u8]>) -> Self {
+        self.config.tls_client_ca = Some(tonic::transport::Certificate::from_pem(ca));
+        self
+    }
+
+    /// Sets the `TCP_NODELAY` option on accepted connections.
+    pub fn tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.config.tcp_nodelay = enabled;
+        self
+    }
+
+    /// Sets the interval of HTTP/2 keepalive pings.
+    pub fn http2_keepalive_interval(mut self, interval: impl Into<Option<Duration>>) -> Self {
+        self.config.http2_keepalive_interval = interval.into();
+        self
+    }
+
+    /// Sets the timeout for receiving an acknowledgement of HTTP/2 keepalive pings.
+    pub fn http2_keepalive_timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.config.http2_keepalive_timeout = timeout.into();
+        self
+    }
+
+    /// Sets the maximum number of concurrent HTTP/2 streams per connection.
+    pub fn max_concurrent_streams(mut self, max: impl Into<Option<u32>>) -> Self {
+        self.config.max_concurrent_streams = max.into();
+        self
+    }
+
+    /// Sets the maximum HTTP/2 frame size.
+    pub fn max_frame_size(mut self, size: impl Into<Option<u32>>) -> Self {
+        self.config.max_frame_size = size.into();
+        self
+    }
+
+    /// Sets the maximum size of a request message in bytes, like tonic's `max_decoding_message_size`.
+    ///
+    /// The limit applies to each message of a streaming request. Requests with larger messages are
+    /// rejected with `RESOURCE_EXHAUSTED` as soon as the message's length-prefix is received.
+    pub fn max_message_size(mut self, size: impl Into<Option<usize>>) -> Self {
+        self.config.max_message_size = size.into();
+        self
+    }
+
+    /// Records mocks instead of serving them: requests for any method are forwarded to `upstream`
+    /// and its responses returned to clients.
+    ///
+    /// Request and response pairs are written to YAML mock files in `dir`, one per method at
+    /// `<service>/<method>.yaml`, converting bodies using message types from `descriptors`.
+    /// Existing files for recorded methods are overwritten. Requests and responses are streamed
+    /// through, and each call is recorded, with its gRPC status and metadata, once its response ends.
+    pub fn record(
+        mut self,
+        upstream: Channel,
+        dir: impl Into<PathBuf>,
+        descriptors: impl TypeResolver + Send + Sync + 'static,
+    ) -> Self {
+        self.config.recorder = Some(Arc::new(Recorder::new(
+            upstream,
+            dir.into(),
+            Arc::new(descriptors),
+        )));
+        self
+    }
+
+    /// Forwards requests that don't match any mock to `upstream`, relaying its responses to clients,
+    /// e.g. to mock some methods of a real server.
+    ///
+    /// Requests for methods without mocks are forwarded too, but only for the server's services,
+    /// or services with mocks if it hosts all services in its [`MockSet`]; other requests are
+    /// rejected with `UNIMPLEMENTED`. Forwarded requests are marked as
+    /// [`proxied`](JournalEntry::proxied) in the journal. Requests for methods without mocks are
+    /// streamed through, while requests for methods with mocks are collected to be matched first.
+    pub fn passthrough(mut self, upstream: Channel) -> Self {
+        self.config.passthrough = Some(upstream);
+        self
+    }
+
+    /// Adds a service to host, by fully-qualified name, e.g. `example.Hello`.
+    pub fn service(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        if !self.services.contains(&name) {
+            self.services.push(name);
+        }
+        self
+    }
+
+    /// Adds services to host, by fully-qualified name.
+    pub fn services(self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        names
+            .into_iter()
+            .fold(self, |builder, name| builder.service(name))
+    }
+
+    /// Builds a [`MockServer`].
+    ///
+    /// If no services were added, the server hosts all services in `mocks`.
+    pub fn build(self, mocks: MockSet) -> Result<MockServer, Error> {
+        let services = self.services;
+        validate_mocks(&services, &mocks)?;
+        Ok(MockServer {
+            addr: self.config.addr,
+            uds_path: None,
+            config: self.config,
+            state: Arc::new(MockServerState::new(services, mocks)),
+            inner: Arc::default(),
+        })
+    }
+
+    /// Builds and starts a [`MockServer`].
+    pub async fn start(self, mocks: MockSet) -> Result<MockServer, Error> {
+        let mut server = self.build(mocks)?;
+        let service = server.clone();
+        server._serve(service).await?;
+        Ok(server)
+    }
+}
+
+/// Configuration for a [`MockServer`].
+#[derive(Debug, Clone)]
+struct MockServerConfig {
+    addr: SocketAddr,
+    transport: Transport,
+    tcp_nodelay: bool,
+    http2_keepalive_interval: Option<Duration>,
+    http2_keepalive_timeout: Option<Duration>,
+    max_concurrent_streams: Option<u32>,
+    max_frame_size: Option<u32>,
+    max_message_size: Option<usize>,
+    health: bool,
+    file_descriptor_sets: Vec<Bytes>,
+    recorder: Option<Arc<Recorder>>,
+    passthrough: Option<Channel>,
+    #[cfg(feature = "tls")]
+    tls_identity: Option<tonic::transport::Identity>,
+    #[cfg(feature = "tls")]
+    tls_client_ca: Option<tonic::transport::Certificate>,
+}
+
+impl Default for MockServerConfig {
+    fn default() -> Self {
+        Self {
+            addr: ([0, 0, 0, 0], 0).into(),
+            transport: Transport::Tcp,
+            tcp_nodelay: false,
+            http2_keepalive_interval: None,
+            http2_keepalive_timeout: None,
+            max_concurrent_streams: None,
+            max_frame_size: None,
+            max_message_size: None,
+            health: false,
+            file_descriptor_sets: Vec::new(),
+            recorder: None,
+            passthrough: None,
+            #[cfg(feature = "tls")]
+            tls_identity: None,
+            #[cfg(feature = "tls")]
+            tls_client_ca: None,
+        }
+    }
+}
+
+/// A transport for a [`MockServer`].
+#[derive(Debug, Clone, Copy)]
+enum Transport {
+    Tcp,
+    #[cfg(unix)]
+    Uds,
+    InMemory,
+}
+
+/// A stream of incoming connections that signals when the server starts accepting.
+struct Incoming<S> {
+    inner: S,
+    ready: Option<oneshot::Sender<()>>,
+}
+
+impl<S> Incoming<S> {
+    fn new(inner: S, ready: oneshot::Sender<()>) -> Self {
+        Self {
+            inner,
+            ready: Some(ready),
+        }
+    }
+}
+
+impl<S: Stream + Unpin> Stream for Incoming<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(ready) = self.ready.take() {
+            let _ = ready.send(());
+        }
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+/// Waits until a spawned server is accepting connections, returning its error if it exits first.
+async fn wait_until_ready(
+    mut handle: JoinHandle<Result<(), tonic::transport::Error>>,
+    ready: oneshot::Receiver<()>,
+) -> Result<JoinHandle<Result<(), tonic::transport::Error>>, Error> {
+    // `ready` resolves once the server polls for connections; if it is dropped
+    // instead, the server task has exited and its result is surfaced below.
+    tokio::select! {
+        Ok(()) = ready => Ok(handle),
+        result = &mut handle => {
+            let error = match result {
+                Ok(Ok(())) => Error::ServerError("server exited unexpectedly".into()),
+                Ok(Err(error)) => error.into(),
+                Err(error) => Error::ServerError(error.to_string()),
+            };
+            Err(error)
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 struct Inner {
-    handle: tokio::task::JoinHandle<Result<(), tonic::transport::Error>>,
+    handle: JoinHandle<Result<(), tonic::transport::Error>>,
+    uds_dir: Option<TempDir>,
+    connections: Option<mpsc::UnboundedSender<DuplexStream>>,
+}
+
+/// Updates health statuses as advertised services change, until the server's state is dropped.
+///
+/// Added services report `SERVING`, and statuses of removed services are cleared.
+async fn update_health(
+    mut reporter: HealthReporter,
+    mut advertised: Vec<String>,
+    mut updates: mpsc::UnboundedReceiver<Vec<String>>,
+) {
+    while let Some(services) = updates.recv().await {
+        for service in advertised.iter().filter(|name| !services.contains(name)) {
+            reporter.clear_service_status(service).await;
+        }
+        for service in services.iter().filter(|name| !advertised.contains(name)) {
+            reporter
+                .set_service_status(service, ServingStatus::Serving)
+                .await;
+        }
+        advertised = services;
+    }
+}
+
+/// Builds server reflection services advertising `services`, and the health service if enabled.
+fn build_reflection(config: &MockServerConfig, services: &[String]) -> Result<Reflection, Error> {
+    let builder = |reflection_service: &str| {
+        let mut builder =
+            tonic_reflection::server::Builder::configure().with_service_name(reflection_service);
+        for name in services {
+            builder = builder.with_service_name(name);
+        }
+        if config.health {
+            builder = builder.with_service_name("grpc.health.v1.Health");
+        }
+        for set in &config.file_descriptor_sets {
+            builder = builder.register_encoded_file_descriptor_set(set);
+        }
+        builder
+    };
+    let invalid = |error| Error::Invalid(format!("invalid file descriptor set: {error}"));
+    Ok(Reflection {
+        v1: BoxCloneService::new(
+            builder("grpc.reflection.v1.ServerReflection")
+                .build_v1()
+                .map_err(invalid)?,
+        ),
+        v1alpha: BoxCloneService::new(
+            builder("grpc.reflection.v1alpha.ServerReflection")
+                .build_v1alpha()
+                .map_err(invalid)?,
+        ),
+    })
+}
+
+/// Returns the DER-encoded certificate chain presented by a TLS client.
+#[cfg(feature = "tls")]
+fn client_certs(extensions: &http::Extensions) -> Vec<Bytes> {
+    use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+
+    let certs = extensions
+        .get::<TlsConnectInfo<TcpConnectInfo>>()
+        .and_then(|info| info.peer_certs())
+        .or_else(|| {
+            extensions
+                .get::<TlsConnectInfo<()>>()
+                .and_then(|info| info.peer_certs())
+        });
+    #[cfg(unix)]
+    let certs = certs.or_else(|| {
+        extensions
+            .get::<TlsConnectInfo<tonic::transport::server::UdsConnectInfo>>()
+            .and_then(|info| info.peer_certs())
+    });
+    certs
+        .map(|certs| {
+            certs
+                .iter()
+                .map(|cert| Bytes::copy_from_slice(cert))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(not(feature = "tls"))]
+fn client_certs(_extensions: &http::Extensions) -> Vec<Bytes> {
+    Vec::new()
+}
+
+/// Returns an error if `mocks` are not all for `services` (unless empty), or don't match their methods' kinds.
+fn validate_mocks(services: &[String], mocks: &MockSet) -> Result<(), Error> {
+    if !services.is_empty()
+        && mocks
+            .iter()
+            .any(|(method, _)| !services.iter().any(|name| name == method.service()))
+    {
+        let names = services
+            .iter()
+            .map(|name| format!("`{name}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let noun = if services.len() == 1 {
+            "service"
+        } else {
+            "services"
+        };
+        return Err(Error::Invalid(format!(
+            "all mocks must be for {names} {noun}"
+        )));
+    }
+    mocks.validate()
+}
+
+/// Collects a request body, rejecting messages larger than `max_message_size` as they are received.
+async fn collect_body<B>(body: B, max_message_size: Option<usize>) -> Result<Bytes, tonic::Status>
+where
+    B: Body,
+    B::Error: Into<StdError>,
+{
+    let mut body = std::pin::pin!(body);
+    let mut buf = BytesMut::new();
+    // Offset of the next message's length-prefix
+    let mut next = 0;
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|error| {
+            let status = tonic::Status::from_error(error.into());
+            match status.code() {
+                Code::Unknown => tonic::Status::internal(status.message()),
+                _ => status,
+            }
+        })?;
+        let Ok(mut data) = frame.into_data() else {
+            continue;
+        };
+        buf.extend_from_slice(&data.copy_to_bytes(data.remaining()));
+        if !check_message_sizes(&buf, &mut next, max_message_size) {
+            return Err(tonic::Status::resource_exhausted(
+                "request exceeds maximum message size",
+            ));
+        }
+    }
+    Ok(buf.freeze())
+}
+
+/// Adds a forwarded call to the journal, with the request body read so far, once it ends or is dropped,
+/// so calls that fail or are cancelled by the client are added too.
+struct JournalGuard {
+    state: Arc<MockServerState>,
+    entry: Option<JournalEntry>,
+    request: Arc<Mutex<BytesMut>>,
+}
+
+impl JournalGuard {
+    /// Adds the call to the journal if not added yet, returning the request body read so far.
+    fn finish(&mut self) -> Bytes {
+        let body = self.request.lock().unwrap().clone().freeze();
+        if let Some(entry) = self.entry.take() {
+            self.state.journal.push(JournalEntry {
+                body: body.clone(),
+                ..entry
+            });
+        }
+        body
+    }
+}
+
+impl Drop for JournalGuard {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+/// Streams a request to `upstream` and its response back to the client, recording the call if recording.
+///
+/// The call is added to the journal once the response ends, fails, or is dropped by the client.
+async fn stream_to_upstream<B>(
+    state: Arc<MockServerState>,
+    recorder: Option<Arc<Recorder>>,
+    upstream: Channel,
+    method: GrpcMethod,
+    parts: http::request::Parts,
+    body: B,
+    max_message_size: Option<usize>,
+) -> Response<BoxBody>
+where
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<StdError>,
+{
+    let body =
+        body.map_frame(|frame| frame.map_data(|mut data| data.copy_to_bytes(data.remaining())));
+    let request = Tee::new(tonic::body::boxed(body)).max_message_size(max_message_size);
+    let mut journal = JournalGuard {
+        state,
+        entry: Some(JournalEntry {
+            method: method.clone(),
+            headers: parts.headers.clone(),
+            body: Bytes::new(),
+            matched: false,
+            proxied: recorder.is_none(),
+            client_certs: client_certs(&parts.extensions),
+        }),
+        request: request.data(),
+    };
+    let request = tonic::body::boxed(request);
+    let response = match proxy::forward(upstream, &method, parts.headers, request).await {
+        Ok(response) => response,
+        Err(status) => {
+            warn!(%method, %status, "failed to forward request");
+            return grpc_response(
+                status.code(),
+                tonic::body::empty_body(),
+                Some(status.message()),
+            );
+        }
+    };
+    let (response_parts, body) = response.into_parts();
+    let headers = response_parts.headers.clone();
+    // Dropped with the guard if the response fails or is dropped before it ends
+    let body = Tee::new(body).on_end(move |body, trailers| {
+        let request = journal.finish();
+        let response = UpstreamResponse {
+            headers,
+            body,
+            trailers,
+        };
+        match recorder.map(|recorder| recorder.record(&method, request, response)) {
+            Some(Ok(())) => info!(%method, "recorded request"),
+            Some(Err(error)) => warn!(%method, %error, "failed to record request"),
+            None => info!(%method, "request forwarded to upstream"),
+        }
+    });
+    Response::from_parts(response_parts, tonic::body::boxed(body))
+}
+
+/// Builds a response for a mock, sending its headers, and its trailers after the body.
+fn mock_response(mock: &MockResponse) -> Response<BoxBody> {
+    let code = mock.grpc_code();
+    let mut response = if mock.trailers().is_empty() {
+        grpc_response(code, mock.body().to_boxed(), mock.error())
+    } else {
+        let mut trailers = mock.trailers().clone();
+        let status = tonic::Status::new(code, mock.error().unwrap_or_default());
+        if let Err(error) = status.add_header(&mut trailers) {
+            warn!(%error, "invalid grpc status");
+            trailers.insert("grpc-status", (code as i32).into());
+        }
+        let data = match mock.body() {
+            MockBody::Empty => Vec::new(),
+            MockBody::Full(data) => vec![data.clone()],
+            MockBody::Stream(data) => data.clone(),
+        };
+        let frames = data
+            .into_iter()
+            .map(Frame::data)
+            .chain([Frame::trailers(trailers)])
+            .map(Ok::<_, Infallible>);
+        let mut response = Response::new(tonic::body::boxed(StreamBody::new(
+            futures::stream::iter(frames),
+        )));
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+        response
+    };
+    response.headers_mut().extend(mock.headers().clone());
+    response
 }
 
 /// Builds a gRPC response.
 fn grpc_response<B>(code: Code, body: B, error: Option<&str>) -> Response<B> {
-    let mut builder = Response::builder()
-        .status(200)
-        .header("content-type", "application/grpc")
-        .header("grpc-status", code as i32);
-    if let Some(error) = error {
-        builder = builder.header("grpc-message", error);
-    }
-    builder.body(body).unwrap()
+    let mut response = Response::new(body);
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+    // Percent-encodes the message, which may not be a valid header value otherwise
+    let status = tonic::Status::new(code, error.unwrap_or_default());
+    if let Err(error) = status.add_header(headers) {
+        warn!(%error, "invalid grpc status");
+        headers.insert("grpc-status", (code as i32).into());
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_until_ready_with_server_exit() {
+        // A server that exits before polling for connections fails to start
+        let (ready_tx, ready_rx) = oneshot::channel::<()>();
+        let handle = tokio::spawn(async move {
+            drop(ready_tx);
+            Ok(())
+        });
+        let result = wait_until_ready(handle, ready_rx).await;
+        assert!(result.is_err_and(|error| matches!(error, Error::ServerError(_))));
+
+        // As does a server that panics
+        let (ready_tx, ready_rx) = oneshot::channel::<()>();
+        let handle = tokio::spawn(async move {
+            let _ready_tx = ready_tx;
+            panic!("failed to start");
+        });
+        let result = wait_until_ready(handle, ready_rx).await;
+        assert!(result.is_err_and(|error| error.to_string().contains("panic")));
+
+        // A server that polls for connections is ready
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            let _ = ready_tx.send(());
+            std::future::pending().await
+        });
+        let handle = wait_until_ready(handle, ready_rx).await.unwrap();
+        handle.abort();
+    }
 }
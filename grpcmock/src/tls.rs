@@ -0,0 +1,79 @@
+use bytes::Bytes;
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+    KeyUsagePurpose,
+};
+
+use crate::Error;
+
+/// A PEM-encoded certificate and private key.
+#[derive(Debug, Clone)]
+pub struct CertifiedKey {
+    pub cert: String,
+    pub key: String,
+    /// DER-encoded certificate, as recorded in [`JournalEntry::client_certs`].
+    ///
+    /// [`JournalEntry::client_certs`]: crate::journal::JournalEntry::client_certs
+    pub cert_der: Bytes,
+}
+
+/// A throwaway self-signed certificate authority for testing TLS and mutual TLS.
+pub struct TestCa {
+    cert: rcgen::Certificate,
+    key: KeyPair,
+}
+
+impl TestCa {
+    /// Generates a new self-signed [`TestCa`].
+    pub fn generate() -> Result<Self, Error> {
+        let key = KeyPair::generate()?;
+        let mut params = CertificateParams::default();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "grpcmock test CA");
+        params.key_usages = vec![
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+            KeyUsagePurpose::DigitalSignature,
+        ];
+        let cert = params.self_signed(&key)?;
+        Ok(Self { cert, key })
+    }
+
+    /// Returns the CA's PEM-encoded certificate.
+    pub fn cert(&self) -> String {
+        self.cert.pem()
+    }
+
+    /// Issues a server certificate valid for `names`, e.g. `["localhost"]`.
+    pub fn server_cert(
+        &self,
+        names: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Result<CertifiedKey, Error> {
+        let names = names.into_iter().map(Into::into).collect::<Vec<_>>();
+        let mut params = CertificateParams::new(names)?;
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        self.issue(params)
+    }
+
+    /// Issues a client certificate with the given common name.
+    pub fn client_cert(&self, common_name: &str) -> Result<CertifiedKey, Error> {
+        let mut params = CertificateParams::default();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        self.issue(params)
+    }
+
+    fn issue(&self, params: CertificateParams) -> Result<CertifiedKey, Error> {
+        let key = KeyPair::generate()?;
+        let cert = params.signed_by(&key, &self.cert, &self.key)?;
+        Ok(CertifiedKey {
+            cert: cert.pem(),
+            key: key.serialize_pem(),
+            cert_der: Bytes::copy_from_slice(cert.der()),
+        })
+    }
+}
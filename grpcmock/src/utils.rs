@@ -1,7 +1,5 @@
 use std::net::TcpListener;
 
-use rand::Rng;
-
 pub mod tonic {
     use http::status::InvalidStatusCode;
 
@@ -10,6 +8,10 @@ pub mod tonic {
         fn from_u16(code: u16) -> Result<tonic::Code, InvalidStatusCode>;
         /// Creates a gRPC status code from an equivalent [`http::StatusCode`].
         fn from_http(status_code: http::StatusCode) -> tonic::Code;
+        /// Converts to an equivalent [`http::StatusCode`], the inverse of [`CodeExt::from_http`].
+        ///
+        /// Codes without an equivalent are converted to `500 Internal Server Error`, i.e. `UNKNOWN`.
+        fn to_http(&self) -> http::StatusCode;
     }
 
     impl CodeExt for tonic::Code {
@@ -35,6 +37,19 @@ pub mod tonic {
                 _ => tonic::Code::Unknown,
             }
         }
+
+        fn to_http(&self) -> http::StatusCode {
+            match self {
+                tonic::Code::Ok => http::StatusCode::OK,
+                tonic::Code::Internal => http::StatusCode::BAD_REQUEST,
+                tonic::Code::Unauthenticated => http::StatusCode::UNAUTHORIZED,
+                tonic::Code::PermissionDenied => http::StatusCode::FORBIDDEN,
+                tonic::Code::NotFound => http::StatusCode::NOT_FOUND,
+                tonic::Code::Unimplemented => http::StatusCode::NOT_IMPLEMENTED,
+                tonic::Code::Unavailable => http::StatusCode::SERVICE_UNAVAILABLE,
+                _ => http::StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        }
     }
 }
 
@@ -64,18 +79,56 @@ pub mod prost {
             buf.freeze()
         }
     }
-}
 
-pub fn find_available_port() -> Option<u16> {
-    let mut rng = rand::thread_rng();
-    loop {
-        let port: u16 = rng.gen_range(40000..60000);
-        if port_is_available(port) {
-            return Some(port);
+    /// Checks the sizes of messages in a HTTP body received so far, from the length-prefix at offset `next`,
+    /// advancing `next` past each complete length-prefix.
+    ///
+    /// Returns `false` if a message is larger than `max_message_size`.
+    pub(crate) fn check_message_sizes(
+        body: &[u8],
+        next: &mut usize,
+        max_message_size: Option<usize>,
+    ) -> bool {
+        while let Some(prefix) = body.get(*next..*next + 5) {
+            let len = u32::from_be_bytes(prefix[1..].try_into().unwrap()) as usize;
+            if max_message_size.is_some_and(|max| len > max) {
+                return false;
+            }
+            *next += 5 + len;
         }
+        true
     }
+
+    /// Splits a HTTP body into the encoded messages of its gRPC frames.
+    pub fn split_frames(mut body: &[u8]) -> Result<Vec<&[u8]>, crate::Error> {
+        let mut messages = Vec::new();
+        while !body.is_empty() {
+            if body.len() < 5 {
+                return Err(crate::Error::Invalid("truncated gRPC frame".into()));
+            }
+            if body[0] != 0 {
+                return Err(crate::Error::Invalid(
+                    "compressed gRPC frames are not supported".into(),
+                ));
+            }
+            let len = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+            let Some(message) = body.get(5..5 + len) else {
+                return Err(crate::Error::Invalid("truncated gRPC frame".into()));
+            };
+            messages.push(message);
+            body = &body[5 + len..];
+        }
+        Ok(messages)
+    }
+}
+
+#[deprecated(note = "servers bind to a port assigned by the OS, see `MockServer::addr`")]
+pub fn find_available_port() -> Option<u16> {
+    let listener = TcpListener::bind(("0.0.0.0", 0)).ok()?;
+    listener.local_addr().ok().map(|addr| addr.port())
 }
 
+#[deprecated(note = "servers bind to a port assigned by the OS, see `MockServer::addr`")]
 pub fn port_is_available(port: u16) -> bool {
     TcpListener::bind(("0.0.0.0", port)).is_ok()
 }
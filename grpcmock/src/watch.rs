@@ -0,0 +1,80 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::{sync::mpsc, task::JoinHandle};
+use tracing::{error, info, warn};
+
+use crate::{mock::MockSet, server::MockServer, Error};
+
+/// Time to wait for further changes before reloading, as editors often write files in multiple steps.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Watches mock files and reloads a [`MockServer`]'s mocks when they change.
+///
+/// Watching stops when dropped.
+pub struct MockWatcher {
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+}
+
+impl Drop for MockWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl MockServer {
+    /// Watches files and directories (recursively) at `paths`, replacing the server's mocks with
+    /// those returned by `load` when they change.
+    ///
+    /// Reloading replaces all of the server's mocks, including those added at runtime with
+    /// [`MockServer::update_mocks`] or the admin API. `load` runs on a blocking thread.
+    ///
+    /// If `load` fails, or returns mocks that are invalid for the server, the error is logged
+    /// and the server keeps its current mocks.
+    pub fn watch<F>(
+        &self,
+        paths: impl IntoIterator<Item = impl AsRef<Path>>,
+        load: F,
+    ) -> Result<MockWatcher, Error>
+    where
+        F: Fn() -> Result<MockSet, Error> + Send + Sync + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<_>| match event {
+                Ok(notify::Event {
+                    kind: EventKind::Access(_),
+                    ..
+                }) => {}
+                Ok(_) => {
+                    let _ = tx.send(());
+                }
+                Err(error) => warn!(%error, "failed to watch mock files"),
+            })?;
+        for path in paths {
+            watcher.watch(path.as_ref(), RecursiveMode::Recursive)?;
+        }
+
+        let server = self.clone();
+        let load = Arc::new(load);
+        let task = tokio::spawn(async move {
+            while rx.recv().await.is_some() {
+                tokio::time::sleep(DEBOUNCE).await;
+                while rx.try_recv().is_ok() {}
+                let load = load.clone();
+                let mocks = tokio::task::spawn_blocking(move || load())
+                    .await
+                    .unwrap_or_else(|error| Err(Error::ServerError(error.to_string())));
+                match mocks.and_then(|mocks| server.set_mocks(mocks)) {
+                    Ok(()) => info!("reloaded mocks"),
+                    Err(error) => error!(%error, "failed to reload mocks, keeping current mocks"),
+                }
+            }
+        });
+        Ok(MockWatcher {
+            _watcher: watcher,
+            task,
+        })
+    }
+}
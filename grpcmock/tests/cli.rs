@@ -0,0 +1,150 @@
+//! Tests of the standalone `grpcmock` binary.
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream},
+    process::{Child, Command, Stdio},
+    sync::mpsc,
+    time::Duration,
+};
+
+use tonic::transport::Endpoint;
+use tonic_health::pb::{health_client::HealthClient, HealthCheckRequest};
+
+const BIN: &str = env!("CARGO_BIN_EXE_grpcmock");
+
+/// A running `grpcmock` process, killed when dropped.
+struct Server {
+    child: Child,
+    lines: mpsc::Receiver<String>,
+}
+
+impl Server {
+    fn start(args: &[&str]) -> Self {
+        let mut child = Command::new(BIN)
+            .args(args)
+            .env("NO_COLOR", "1")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let _ = tx.send(line);
+            }
+        });
+        Self { child, lines }
+    }
+
+    /// Waits for a log line containing `message`, returning the address following it.
+    fn addr(&self, message: &str) -> SocketAddr {
+        loop {
+            let line = self
+                .lines
+                .recv_timeout(Duration::from_secs(10))
+                .unwrap_or_else(|_| panic!("server did not log `{message}`"));
+            if let Some((_, rest)) = line.split_once(message) {
+                let addr = rest.split(|c: char| c.is_whitespace() || c == '\x1b');
+                return addr.into_iter().next().unwrap().parse().unwrap();
+            }
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Sends an HTTP/1.1 GET request, returning the response body.
+fn http_get(addr: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nhost: {addr}\r\nconnection: close\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+    body.to_string()
+}
+
+#[test]
+fn test_args() {
+    let output = Command::new(BIN).arg("--help").output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--stubs <DIR>"));
+
+    // Descriptors and stubs are required
+    let output = Command::new(BIN)
+        .args(["--stubs", "stubs"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let output = Command::new(BIN)
+        .args(["--proto", "example.proto"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    // Invalid mock files fail startup
+    let output = Command::new(BIN)
+        .args(["--proto", "example.proto", "-I", "../grpcmock-test/proto"])
+        .args(["--stubs", "../grpcmock-test/stubs/invalid"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[tokio::test]
+async fn test_serve() -> Result<(), Box<dyn std::error::Error>> {
+    let server = Server::start(&[
+        "--proto",
+        "example.proto",
+        "-I",
+        "../grpcmock-test/proto",
+        "--stubs",
+        "../grpcmock-test/stubs/hello",
+        "--addr",
+        "127.0.0.1:0",
+        "--admin-addr",
+        "127.0.0.1:0",
+        "--health",
+    ]);
+    let addr = server.addr("listening on ");
+    let admin_addr = server.addr("admin API listening on ");
+
+    let mocks: serde_json::Value = serde_json::from_str(&http_get(admin_addr, "/mocks"))?;
+    let methods = mocks
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file| file["method"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        methods,
+        [
+            "HelloBidiStreaming",
+            "HelloClientStreaming",
+            "HelloServerStreaming",
+            "HelloUnary"
+        ]
+    );
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))?
+        .connect()
+        .await?;
+    let mut client = HealthClient::new(channel);
+    let response = client
+        .check(HealthCheckRequest {
+            service: "example.Hello".into(),
+        })
+        .await?;
+    assert_eq!(response.into_inner().status, 1);
+
+    Ok(())
+}